@@ -0,0 +1,63 @@
+//! Fill a solution into the puzzle's web page from the browser console.
+
+use std::fmt::Write;
+
+use crate::{Board, CellState};
+
+/// The page element for each cell, in reading order. This assumes the page draws each cell as an
+/// element with class `cell`; pass another selector if it doesn't.
+pub const DEFAULT_SELECTOR: &str = ".cell";
+
+/// The flooded cells of a solved board as (ix, iy), in reading order
+pub fn flooded_cells(board: &Board) -> Vec<(usize, usize)> {
+    (0..board.height)
+        .flat_map(|iy| (0..board.width).map(move |ix| (ix, iy)))
+        .filter(|&(ix, iy)| board.cell_state_at(ix, iy) == CellState::Flooded)
+        .collect()
+}
+
+/// A JavaScript snippet that clicks each flooded cell of the solved board.
+/// It checks the page has a cell for every cell of the board before clicking anything.
+pub fn snippet(board: &Board, selector: &str) -> String {
+    let indices: Vec<_> = flooded_cells(board)
+        .iter()
+        .map(|&(ix, iy)| (iy * board.width + ix).to_string())
+        .collect();
+
+    let mut out = String::new();
+    writeln!(out, "(() => {{").unwrap();
+    writeln!(
+        out,
+        "  const cells = document.querySelectorAll({:?});",
+        selector
+    )
+    .unwrap();
+    writeln!(
+        out,
+        "  if (cells.length !== {}) {{",
+        board.width * board.height
+    )
+    .unwrap();
+    writeln!(
+        out,
+        "    throw new Error(`Expected {} cells, found ${{cells.length}}`);",
+        board.width * board.height
+    )
+    .unwrap();
+    writeln!(out, "  }}").unwrap();
+    writeln!(out, "  for (const i of [{}]) {{", indices.join(", ")).unwrap();
+    writeln!(
+        out,
+        "    for (const type of [\"mousedown\", \"mouseup\", \"click\"]) {{"
+    )
+    .unwrap();
+    writeln!(
+        out,
+        "      cells[i].dispatchEvent(new MouseEvent(type, {{ bubbles: true, button: 0 }}));"
+    )
+    .unwrap();
+    writeln!(out, "    }}").unwrap();
+    writeln!(out, "  }}").unwrap();
+    writeln!(out, "}})();").unwrap();
+    out
+}
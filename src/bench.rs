@@ -0,0 +1,273 @@
+//! A/B comparison of two solver configurations over the same puzzles.
+//!
+//! A configuration is written as settings joined with `+`, e.g. `rules=r2,r4+heuristic=index`:
+//!
+//! - `rules=<list>` enables only the listed rules, `r1` to `r6`
+//! - `heuristic=index|constrained|dom-wdeg` picks how the search branches
+//! - `backend=rules|search` runs the rules and then the search for what they leave, or the search
+//!   alone
+//!
+//! `default` is the solver as it ships. Each puzzle is solved a few times with each configuration
+//! and the fastest time kept, as the slower runs mostly measure the machine.
+
+use std::io::{self, Write};
+use std::time::{Duration, Instant};
+
+use crate::search::{self, Heuristic};
+use crate::stats::BatchEntry;
+use crate::Rule;
+
+/// How to solve
+#[derive(Clone, Debug)]
+pub struct Config {
+    /// The configuration as it was written
+    pub name: String,
+    disabled_rules: Vec<Rule>,
+    heuristic: Heuristic,
+    /// Whether the rules run before the search
+    rules: bool,
+}
+
+impl Config {
+    /// Parse a configuration written as `+`-joined settings, or `default`
+    pub fn parse(text: &str) -> Result<Config, String> {
+        let mut config = Config {
+            name: text.to_string(),
+            disabled_rules: Vec::new(),
+            heuristic: Heuristic::default(),
+            rules: true,
+        };
+        if text == "default" {
+            return Ok(config);
+        }
+        for setting in text.split('+') {
+            let (key, value) = setting
+                .split_once('=')
+                .ok_or_else(|| format!("Bad setting '{}'; expected key=value", setting))?;
+            match key {
+                "rules" => {
+                    let enabled = value
+                        .split(',')
+                        .map(|name| {
+                            Rule::from_name(name).ok_or_else(|| format!("Unknown rule {}", name))
+                        })
+                        .collect::<Result<Vec<_>, _>>()?;
+                    config.disabled_rules = Rule::ALL
+                        .iter()
+                        .copied()
+                        .filter(|rule| !enabled.contains(rule))
+                        .collect();
+                }
+                "heuristic" => {
+                    config.heuristic = Heuristic::from_name(value).ok_or_else(|| {
+                        format!(
+                            "Unknown heuristic {}; expected index, constrained or dom-wdeg",
+                            value
+                        )
+                    })?
+                }
+                "backend" => {
+                    config.rules = match value {
+                        "rules" => true,
+                        "search" => false,
+                        _ => {
+                            return Err(format!(
+                                "Unknown backend {}; expected rules or search",
+                                value
+                            ))
+                        }
+                    }
+                }
+                _ => return Err(format!("Unknown setting {}", key)),
+            }
+        }
+        Ok(config)
+    }
+}
+
+/// How one configuration did on one puzzle
+#[derive(Copy, Clone, Debug)]
+pub struct Sample {
+    /// The fastest of the runs
+    pub elapsed: Duration,
+    /// Deductions made by the rules plus levels tried by the search
+    pub steps: usize,
+    pub solved: bool,
+}
+
+/// How both configurations did on one puzzle
+pub struct Comparison {
+    pub name: String,
+    pub a: Sample,
+    pub b: Sample,
+}
+
+/// Solve every puzzle with each configuration, `repeat` times apiece
+pub fn run(entries: &[BatchEntry], a: &Config, b: &Config, repeat: usize) -> Vec<Comparison> {
+    entries
+        .iter()
+        .map(|entry| Comparison {
+            name: entry.name.clone(),
+            a: sample(entry, a, repeat),
+            b: sample(entry, b, repeat),
+        })
+        .collect()
+}
+
+fn sample(entry: &BatchEntry, config: &Config, repeat: usize) -> Sample {
+    let mut best: Option<Sample> = None;
+    for _ in 0..repeat.max(1) {
+        let mut board = entry.board.clone();
+        board.disabled_rules = config.disabled_rules.clone();
+        let start = Instant::now();
+        let mut steps = 0;
+        if config.rules {
+            steps += board.solve().deductions;
+        }
+        if !board.is_solved() {
+            steps += search::search_with(&mut board, config.heuristic).1.nodes;
+        }
+        let run = Sample {
+            elapsed: start.elapsed(),
+            steps,
+            solved: board.is_solved(),
+        };
+        if best.is_none_or(|best| run.elapsed < best.elapsed) {
+            best = Some(run);
+        }
+    }
+    best.unwrap()
+}
+
+/// Write a line per puzzle with both configurations' times and steps, then the totals, how much
+/// faster or slower B is than A, and on how many puzzles
+pub fn write_report<W: Write>(
+    mut out: W,
+    a: &Config,
+    b: &Config,
+    comparisons: &[Comparison],
+) -> io::Result<()> {
+    writeln!(out, "A: {}", a.name)?;
+    writeln!(out, "B: {}", b.name)?;
+    writeln!(out)?;
+    let name_width = comparisons
+        .iter()
+        .map(|comparison| comparison.name.len())
+        .max()
+        .unwrap_or(0)
+        .max("puzzle".len());
+    writeln!(
+        out,
+        "{:<w$} {:>10} {:>10} {:>8} {:>9} {:>9}",
+        "puzzle",
+        "A ms",
+        "B ms",
+        "B/A",
+        "A steps",
+        "B steps",
+        w = name_width
+    )?;
+    for comparison in comparisons {
+        let mark = |sample: &Sample| if sample.solved { "" } else { "!" };
+        writeln!(
+            out,
+            "{:<w$} {:>10.3} {:>10.3} {:>7.2}x {:>8}{:1} {:>8}{:1}",
+            comparison.name,
+            millis(comparison.a.elapsed),
+            millis(comparison.b.elapsed),
+            ratio(comparison.b.elapsed, comparison.a.elapsed),
+            comparison.a.steps,
+            mark(&comparison.a),
+            comparison.b.steps,
+            mark(&comparison.b),
+            w = name_width
+        )?;
+    }
+    if comparisons.iter().any(|it| !it.a.solved || !it.b.solved) {
+        writeln!(out, "! not solved")?;
+    }
+    writeln!(out)?;
+
+    let total = |pick: fn(&Comparison) -> &Sample| {
+        comparisons
+            .iter()
+            .map(|comparison| pick(comparison).elapsed)
+            .sum::<Duration>()
+    };
+    let solved = |pick: fn(&Comparison) -> &Sample| {
+        comparisons
+            .iter()
+            .filter(|comparison| pick(comparison).solved)
+            .count()
+    };
+    writeln!(
+        out,
+        "{} puzzles; solved by A {}, by B {}",
+        comparisons.len(),
+        solved(|it| &it.a),
+        solved(|it| &it.b)
+    )?;
+    writeln!(
+        out,
+        "Total time: A {:.3} ms, B {:.3} ms",
+        millis(total(|it| &it.a)),
+        millis(total(|it| &it.b))
+    )?;
+
+    let times: Vec<_> = comparisons
+        .iter()
+        .map(|comparison| ratio(comparison.b.elapsed, comparison.a.elapsed))
+        .collect();
+    let steps: Vec<_> = comparisons
+        .iter()
+        .map(|comparison| (comparison.b.steps.max(1) as f64) / (comparison.a.steps.max(1) as f64))
+        .collect();
+    for (what, ratios) in [("Time", &times), ("Steps", &steps)] {
+        let faster = ratios.iter().filter(|&&ratio| ratio < 1.0).count();
+        let slower = ratios.iter().filter(|&&ratio| ratio > 1.0).count();
+        writeln!(
+            out,
+            "{} B/A: geometric mean {:.3}x, median {:.3}x; B lower on {}, higher on {}, \
+             equal on {}",
+            what,
+            geometric_mean(ratios),
+            median(ratios),
+            faster,
+            slower,
+            ratios.len() - faster - slower
+        )?;
+    }
+    Ok(())
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+/// `b / a`, keeping clear of dividing by a zero-length time
+fn ratio(b: Duration, a: Duration) -> f64 {
+    let floor = 1e-9;
+    b.as_secs_f64().max(floor) / a.as_secs_f64().max(floor)
+}
+
+fn geometric_mean(values: &[f64]) -> f64 {
+    if values.is_empty() {
+        return 1.0;
+    }
+    let logs: f64 = values.iter().map(|value| value.ln()).sum();
+    (logs / values.len() as f64).exp()
+}
+
+fn median(values: &[f64]) -> f64 {
+    if values.is_empty() {
+        return 1.0;
+    }
+    let mut sorted = values.to_vec();
+    sorted.sort_by(|a, b| a.total_cmp(b));
+    let middle = sorted.len() / 2;
+    if sorted.len().is_multiple_of(2) {
+        (sorted[middle - 1] + sorted[middle]) / 2.0
+    } else {
+        sorted[middle]
+    }
+}
@@ -0,0 +1,294 @@
+//! A compact binary format for storing many puzzles.
+//!
+//! A file starts with the magic bytes `AQB1`, followed by one record per puzzle, each preceded by
+//! its length in bytes as a varint so a reader can skip over it. A record holds:
+//!
+//! - the width and height, as varints
+//! - the row hints, then the column hints, as varints
+//! - the walls as bits, lowest bit first: for each row, whether a wall stands to the right of
+//!   each cell but the last; then for each row but the last, whether there is a floor under
+//!   each cell
+//!
+//! Varints are little-endian base 128: seven bits per byte, the top bit set on every byte but
+//! the last. Only the layout and hints are kept; names, metadata, labels and cell states are not,
+//! and the aquariums are numbered in reading order when read back.
+
+use std::convert::TryFrom;
+use std::io::Read;
+
+use crate::{regions, Board, HintMode};
+
+pub const MAGIC: &[u8] = b"AQB1";
+
+/// Encode the boards as a file
+pub fn write(boards: &[&Board]) -> Result<Vec<u8>, String> {
+    let mut out = MAGIC.to_vec();
+    for (i, board) in boards.iter().enumerate() {
+        let record = encode(board).map_err(|err| format!("Puzzle {}: {}", i + 1, err))?;
+        write_varint(&mut out, record.len());
+        out.extend(record);
+    }
+    Ok(out)
+}
+
+/// Decode every board in a file
+pub fn read(bytes: &[u8]) -> Result<Vec<Board>, String> {
+    Records::new(bytes)?.collect()
+}
+
+/// Decodes the boards of a file one record at a time, so a file of any size can be read
+pub(crate) struct Records<R> {
+    reader: R,
+    /// The records read so far
+    n_read: usize,
+    /// Set once the file ends, or breaks off so that the records after can't be found
+    done: bool,
+}
+
+impl<R: Read> Records<R> {
+    /// Start reading a file, checking that it begins with the magic bytes
+    pub fn new(mut reader: R) -> Result<Records<R>, String> {
+        let mut magic = [0; 4];
+        if reader.read_exact(&mut magic).is_err() || magic[..] != *MAGIC {
+            return Err("Not a binary puzzle file: it doesn't start with AQB1".to_string());
+        }
+        Ok(Records {
+            reader,
+            n_read: 0,
+            done: false,
+        })
+    }
+
+    /// The bytes of the next record, or None at the end of the file
+    fn record(&mut self) -> Result<Option<Vec<u8>>, String> {
+        // The length's varint runs to the first byte without the top bit set, ten at most
+        let mut length = Vec::new();
+        let mut byte = [0];
+        while length.len() < 10 && length.last().is_none_or(|last| last & 0x80 != 0) {
+            match self.reader.read(&mut byte) {
+                Ok(0) => break,
+                Ok(_) => length.push(byte[0]),
+                Err(err) => return Err(err.to_string()),
+            }
+        }
+        if length.is_empty() {
+            return Ok(None);
+        }
+        let length = Reader {
+            bytes: &length,
+            at: 0,
+        }
+        .varint()?;
+        let mut record = Vec::new();
+        self.reader
+            .by_ref()
+            .take(length as u64)
+            .read_to_end(&mut record)
+            .map_err(|err| err.to_string())?;
+        if record.len() < length {
+            return Err("the file ends inside it".to_string());
+        }
+        Ok(Some(record))
+    }
+}
+
+impl<R: Read> Iterator for Records<R> {
+    type Item = Result<Board, String>;
+
+    /// The next board, or why it couldn't be read. A record that doesn't decode is skipped
+    /// over, but the file ends at one whose length can't be read.
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        self.n_read += 1;
+        let n = self.n_read;
+        match self.record() {
+            Ok(Some(record)) => {
+                Some(decode(&record).map_err(|err| format!("Puzzle {}: {}", n, err)))
+            }
+            Ok(None) => {
+                self.done = true;
+                None
+            }
+            Err(err) => {
+                self.done = true;
+                Some(Err(format!("Puzzle {}: {}", n, err)))
+            }
+        }
+    }
+}
+
+fn encode(board: &Board) -> Result<Vec<u8>, String> {
+    if board.hint_mode != HintMode::Exact {
+        return Err("Only exact hints can be packed".to_string());
+    }
+    let mut walls = Vec::new();
+    for iy in 0..board.height {
+        walls.extend((0..board.width - 1).map(|ix| board.wall_at(ix, iy)));
+    }
+    for iy in 0..board.height - 1 {
+        walls.extend((0..board.width).map(|ix| board.floor_at(ix, iy)));
+    }
+    // The walls only tell the aquariums apart if each aquarium is in one piece
+    if regions(board.width, board.height, &walls).1 != board.n_partitions {
+        return Err("An aquarium is split in two, which walls can't describe".to_string());
+    }
+
+    let mut out = Vec::new();
+    write_varint(&mut out, board.width);
+    write_varint(&mut out, board.height);
+    for &hint in board.row_hints.iter().chain(&board.col_hints) {
+        write_varint(&mut out, usize::try_from(hint).unwrap());
+    }
+    let mut packed = vec![0u8; walls.len().div_ceil(8)];
+    for (i, &wall) in walls.iter().enumerate() {
+        if wall {
+            packed[i / 8] |= 1 << (i % 8);
+        }
+    }
+    out.extend(packed);
+    Ok(out)
+}
+
+fn decode(record: &[u8]) -> Result<Board, String> {
+    let mut reader = Reader {
+        bytes: record,
+        at: 0,
+    };
+    let width = reader.varint()?;
+    let height = reader.varint()?;
+    if width == 0 || height == 0 {
+        return Err("The board is empty".to_string());
+    }
+    // Every cell but one has a wall or floor bit, so a bigger board than this is corrupt
+    let fits = width
+        .checked_mul(height)
+        .is_some_and(|cells| cells <= record.len() * 8 + 1);
+    if !fits {
+        return Err(format!(
+            "A {}x{} board can't fit in the record",
+            width, height
+        ));
+    }
+
+    let mut board = Board::make(width, height);
+    for iy in 0..height {
+        let hint = reader.varint()?;
+        if hint > width {
+            return Err(format!("Row {} hint is more than the width", iy));
+        }
+        board.row_hints[iy] = isize::try_from(hint).unwrap();
+    }
+    for ix in 0..width {
+        let hint = reader.varint()?;
+        if hint > height {
+            return Err(format!("Column {} hint is more than the height", ix));
+        }
+        board.col_hints[ix] = isize::try_from(hint).unwrap();
+    }
+    if board.row_hints.iter().sum::<isize>() != board.col_hints.iter().sum::<isize>() {
+        return Err("Row and column hints don't add up to the same total".to_string());
+    }
+
+    let n_walls = height * (width - 1) + (height - 1) * width;
+    let packed = &record[reader.at..];
+    if packed.len() != n_walls.div_ceil(8) {
+        return Err(format!(
+            "{} bytes of walls, expected {}",
+            packed.len(),
+            n_walls.div_ceil(8)
+        ));
+    }
+    let walls: Vec<_> = (0..n_walls)
+        .map(|i| packed[i / 8] & 1 << (i % 8) != 0)
+        .collect();
+    let (partitions, _) = regions(width, height, &walls);
+    for (cell, partition) in board.cells.iter_mut().zip(partitions) {
+        cell.partition = partition;
+    }
+    board.index_partitions();
+    Ok(board)
+}
+
+fn write_varint(out: &mut Vec<u8>, mut value: usize) {
+    while value >= 0x80 {
+        out.push((value & 0x7f) as u8 | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    at: usize,
+}
+
+impl Reader<'_> {
+    fn varint(&mut self) -> Result<usize, String> {
+        let mut value: usize = 0;
+        for shift in (0..usize::BITS).step_by(7) {
+            let byte = *self
+                .bytes
+                .get(self.at)
+                .ok_or("The record ends inside a number")?;
+            self.at += 1;
+            value |= usize::from(byte & 0x7f)
+                .checked_shl(shift)
+                .filter(|shifted| shifted >> shift == usize::from(byte & 0x7f))
+                .ok_or("A number is too big")?;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err("A number is too big".to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CellState;
+
+    #[test]
+    fn a_board_reads_back_as_written() {
+        let mut board = Board::make_b0();
+        board.set_cell_at(0, 5, CellState::Flooded);
+        let bytes = write(&[&board, &board.mirrored()]).unwrap();
+        let read = read(&bytes).unwrap();
+        assert_eq!(read.len(), 2);
+        for (read, written) in read.iter().zip([board.clone(), board.mirrored()]) {
+            assert_eq!((read.width, read.height), (written.width, written.height));
+            assert_eq!(read.row_hints, written.row_hints);
+            assert_eq!(read.col_hints, written.col_hints);
+            assert_eq!(read.n_partitions, written.n_partitions);
+            for iy in 0..read.height {
+                for ix in 0..read.width - 1 {
+                    assert_eq!(read.wall_at(ix, iy), written.wall_at(ix, iy));
+                }
+            }
+            for iy in 0..read.height - 1 {
+                for ix in 0..read.width {
+                    assert_eq!(read.floor_at(ix, iy), written.floor_at(ix, iy));
+                }
+            }
+            // Cell states aren't kept
+            assert!(read.cells.iter().all(|cell| cell.state == CellState::Empty));
+        }
+    }
+
+    #[test]
+    fn hints_that_dont_add_up_are_rejected() {
+        let mut board = Board::make_b0();
+        let mut record = encode(&board).unwrap();
+        assert!(decode(&record).is_ok());
+        // The first row hint follows the one-byte width and height
+        record[2] += 1;
+        assert_eq!(
+            decode(&record).err().unwrap(),
+            "Row and column hints don't add up to the same total"
+        );
+        board.hint_mode = HintMode::AtLeast;
+        assert!(encode(&board).is_err());
+    }
+}
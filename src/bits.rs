@@ -0,0 +1,47 @@
+//! Lines of cells packed into bitmasks, so counting the set cells in a line is a popcount.
+
+use alloc::vec;
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+
+/// A fixed number of lines of `len` bits each
+#[derive(Clone)]
+pub struct BitLines {
+    /// Words per line
+    stride: usize,
+    words: Vec<u64>,
+}
+
+impl BitLines {
+    /// `n_lines` lines of `len` bits, all clear
+    pub fn new(n_lines: usize, len: usize) -> BitLines {
+        let stride = len.div_ceil(64);
+        BitLines {
+            stride,
+            words: vec![0; n_lines * stride],
+        }
+    }
+
+    pub fn set(&mut self, line: usize, i: usize, value: bool) {
+        let word = &mut self.words[line * self.stride + i / 64];
+        let mask = 1 << (i % 64);
+        if value {
+            *word |= mask;
+        } else {
+            *word &= !mask;
+        }
+    }
+
+    /// Clear every bit in every line
+    pub fn clear(&mut self) {
+        self.words.fill(0);
+    }
+
+    /// The number of set bits in the line
+    pub fn count(&self, line: usize) -> isize {
+        // `count_ones` compiles down to the popcount instruction where the target has one
+        let words = &self.words[line * self.stride..(line + 1) * self.stride];
+        let count: u32 = words.iter().map(|word| word.count_ones()).sum();
+        isize::try_from(count).unwrap()
+    }
+}
@@ -0,0 +1,282 @@
+//! Solve every puzzle file under a directory, or a single corpus file, and summarise how it went.
+
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread;
+use std::time::Duration;
+
+use crate::format;
+use crate::search;
+use crate::stats::{BatchEntry, Metadata, SolveStats};
+use crate::unsat;
+use crate::Board;
+
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub enum Status {
+    Solved,
+    /// The puzzle was read, but no solution was found
+    Stalled,
+    /// The solve was cancelled for taking too long
+    TimedOut,
+    /// The file couldn't be read as a puzzle
+    Invalid,
+}
+
+impl Status {
+    fn name(self) -> &'static str {
+        match self {
+            Status::Solved => "solved",
+            Status::Stalled => "stalled",
+            Status::TimedOut => "timeout",
+            Status::Invalid => "invalid",
+        }
+    }
+}
+
+/// How one puzzle went
+pub struct Outcome {
+    /// The file's path, followed by `#n` for the nth puzzle of a file holding several
+    pub id: String,
+    pub status: Status,
+    pub difficulty: String,
+    pub metadata: Metadata,
+    pub size: Option<(usize, usize)>,
+    pub elapsed: Duration,
+    /// The solved board, one string per row
+    pub solution: Option<Vec<String>>,
+    /// Why the file is invalid, or which hints keep a stalled puzzle from having a solution
+    pub error: Option<String>,
+}
+
+/// Solve each puzzle in the file at `path`, or in the files under it if it's a directory, in path
+/// order, giving up on any that takes longer than `timeout`. Each outcome is handed to
+/// `on_outcome` as soon as the puzzle is done. The files are read a puzzle at a time, so only
+/// the outcomes `on_outcome` keeps stay in memory.
+pub fn run<F: FnMut(Outcome)>(
+    path: &Path,
+    timeout: Option<Duration>,
+    mut on_outcome: F,
+) -> io::Result<()> {
+    for path in puzzle_files(path)? {
+        let puzzles = match format::stream_file(&path) {
+            Ok(puzzles) => puzzles,
+            Err(error) => {
+                on_outcome(invalid(path.display().to_string(), error));
+                continue;
+            }
+        };
+        for puzzle in puzzles {
+            let id = puzzle.id(&path);
+            on_outcome(match puzzle.entry {
+                Ok(entry) => solve_entry(id, entry, timeout),
+                Err(error) => invalid(id, error),
+            });
+        }
+    }
+    Ok(())
+}
+
+/// The puzzle files under `path`, in path order, or just `path` if it's a file
+pub fn puzzle_files(path: &Path) -> io::Result<Vec<PathBuf>> {
+    if !path.is_dir() {
+        return Ok(vec![path.to_path_buf()]);
+    }
+    let mut paths = Vec::new();
+    collect_puzzle_files(path, &mut paths)?;
+    paths.sort();
+    Ok(paths)
+}
+
+fn collect_puzzle_files(dir: &Path, paths: &mut Vec<PathBuf>) -> io::Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_puzzle_files(&path, paths)?;
+        } else if format::is_puzzle_file(&path) {
+            paths.push(path);
+        }
+    }
+    Ok(())
+}
+
+fn invalid(id: String, error: String) -> Outcome {
+    Outcome {
+        id,
+        status: Status::Invalid,
+        difficulty: String::new(),
+        metadata: Metadata::default(),
+        size: None,
+        elapsed: Duration::default(),
+        solution: None,
+        error: Some(error),
+    }
+}
+
+fn solve_entry(id: String, mut entry: BatchEntry, timeout: Option<Duration>) -> Outcome {
+    let puzzle = entry.board.clone();
+    let stats = match timeout {
+        Some(timeout) => solve_within(&mut entry.board, timeout),
+        None => search::solve(&mut entry.board),
+    };
+    let solved = entry.board.is_solved();
+    let status = if solved {
+        Status::Solved
+    } else if stats.stopped {
+        Status::TimedOut
+    } else {
+        Status::Stalled
+    };
+    let error = match status {
+        Status::Stalled => {
+            unsat::conflicting_hints(&puzzle).map(|conflict| conflict.describe(&puzzle))
+        }
+        _ => None,
+    };
+    Outcome {
+        id,
+        status,
+        difficulty: entry.difficulty,
+        metadata: entry.metadata,
+        size: Some((entry.board.width, entry.board.height)),
+        elapsed: stats.elapsed,
+        solution: if solved {
+            Some(solution_rows(&entry.board))
+        } else {
+            None
+        },
+        error,
+    }
+}
+
+/// Solve, cancelling the solve from a watchdog thread if it runs past `timeout`
+fn solve_within(board: &mut Board, timeout: Duration) -> SolveStats {
+    let cancelled = AtomicBool::new(false);
+    let (done, finished) = mpsc::channel::<()>();
+    thread::scope(|scope| {
+        let cancelled = &cancelled;
+        scope.spawn(move || {
+            // The sender is dropped once the solve is over, which ends the wait early
+            if finished.recv_timeout(timeout) == Err(RecvTimeoutError::Timeout) {
+                cancelled.store(true, Ordering::Relaxed);
+            }
+        });
+        let stats = search::solve_cancellable(board, cancelled);
+        drop(done);
+        stats
+    })
+}
+
+fn solution_rows(board: &Board) -> Vec<String> {
+    (0..board.height)
+        .map(|iy| {
+            (0..board.width)
+                .map(|ix| board.cell_state_at(ix, iy).rep())
+                .collect()
+        })
+        .collect()
+}
+
+/// Write a table with a line per puzzle, followed by the totals
+pub fn write_summary<W: Write>(mut out: W, outcomes: &[Outcome]) -> io::Result<()> {
+    writeln!(
+        out,
+        "{:<8} {:>10} {:<10} {:>7}  puzzle",
+        "status", "time (ms)", "difficulty", "size"
+    )?;
+    for outcome in outcomes {
+        let size = match outcome.size {
+            Some((width, height)) => format!("{}x{}", width, height),
+            None => "-".to_string(),
+        };
+        write!(
+            out,
+            "{:<8} {:>10.3} {:<10} {:>7}  {}",
+            outcome.status.name(),
+            outcome.elapsed.as_secs_f64() * 1000.0,
+            outcome.difficulty,
+            size,
+            outcome.id
+        )?;
+        if let Some(error) = &outcome.error {
+            write!(out, ": {}", error)?;
+        }
+        writeln!(out)?;
+    }
+
+    let count = |status| {
+        outcomes
+            .iter()
+            .filter(|outcome| outcome.status == status)
+            .count()
+    };
+    let elapsed: Duration = outcomes.iter().map(|outcome| outcome.elapsed).sum();
+    writeln!(
+        out,
+        "\n{} puzzles: {} solved, {} stalled, {} timed out, {} invalid in {:.3} ms",
+        outcomes.len(),
+        count(Status::Solved),
+        count(Status::Stalled),
+        count(Status::TimedOut),
+        count(Status::Invalid),
+        elapsed.as_secs_f64() * 1000.0
+    )
+}
+
+/// Write the outcome as a single line of JSON:
+/// `{"id": ..., "status": ..., "difficulty": ..., "time_us": ..., "solution": [rows] or null}`,
+/// with a `"metadata"` object holding whichever of the puzzle's metadata it gives, and an
+/// `"error"` for invalid files and stalled puzzles
+pub fn write_json_line<W: Write>(mut out: W, outcome: &Outcome) -> io::Result<()> {
+    write!(
+        out,
+        "{{\"id\":{},\"status\":{},\"difficulty\":{},\"time_us\":{},\"solution\":",
+        json_string(&outcome.id),
+        json_string(outcome.status.name()),
+        json_string(&outcome.difficulty),
+        outcome.elapsed.as_micros()
+    )?;
+    match &outcome.solution {
+        Some(rows) => {
+            let rows: Vec<_> = rows.iter().map(|row| json_string(row)).collect();
+            write!(out, "[{}]", rows.join(","))?;
+        }
+        None => write!(out, "null")?,
+    }
+    let metadata = &outcome.metadata;
+    let fields: Vec<_> = [
+        ("source", &metadata.source),
+        ("id", &metadata.id),
+        ("author", &metadata.author),
+        ("date", &metadata.date),
+    ]
+    .iter()
+    .filter(|(_, value)| !value.is_empty())
+    .map(|(key, value)| format!("{}:{}", json_string(key), json_string(value)))
+    .collect();
+    if !fields.is_empty() {
+        write!(out, ",\"metadata\":{{{}}}", fields.join(","))?;
+    }
+    if let Some(error) = &outcome.error {
+        write!(out, ",\"error\":{}", json_string(error))?;
+    }
+    writeln!(out, "}}")?;
+    out.flush()
+}
+
+/// The text as a quoted JSON string
+pub fn json_string(text: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            c if c.is_control() => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
@@ -0,0 +1,91 @@
+//! A solve recorded as an asciinema cast, to play back on a web page with the standard player.
+//!
+//! The file is asciicast version 2: a JSON header line giving the terminal size, then one JSON
+//! array per line for each burst of output, `[seconds, "o", text]`. Each frame clears the screen
+//! and draws the board in its working state with the deduction just made underneath, a fixed
+//! delay after the last.
+
+use std::fmt::Write;
+
+use crate::campaign::json_string;
+use crate::observer::SolverObserver;
+use crate::{search, Board, Deduction, RenderMode, RenderOptions};
+
+/// Moves the cursor home and clears the screen, so each frame replaces the last
+const CLEAR: &str = "\x1b[H\x1b[2J";
+
+/// Draws a frame for every deduction the solver makes
+struct Recorder {
+    options: RenderOptions,
+    frames: Vec<String>,
+}
+
+impl Recorder {
+    fn frame(&mut self, board: &Board, caption: &str) {
+        let mut frame = board.render_with(self.options);
+        frame.push_str(caption);
+        self.frames.push(frame);
+    }
+}
+
+impl SolverObserver for Recorder {
+    fn on_deduction(&mut self, board: &Board, deduction: &Deduction) -> bool {
+        self.frame(board, &deduction.to_string());
+        true
+    }
+}
+
+/// Solve `board` from its current state and record it as a cast called `title`, with a frame
+/// every `delay` seconds: the puzzle, each deduction, and the finished board
+pub fn record(title: &str, board: &Board, delay: f64) -> String {
+    let mut board = board.clone();
+    let mut recorder = Recorder {
+        options: RenderOptions {
+            mode: RenderMode::Working,
+            ..RenderOptions::default()
+        },
+        frames: Vec::new(),
+    };
+    recorder.frame(&board, "");
+    let stats = search::solve_observed(&mut board, &mut recorder);
+    let result = if !board.is_solved() {
+        "Not solved".to_string()
+    } else if stats.guesses > 0 {
+        format!("Solved, with {} guesses", stats.guesses)
+    } else {
+        "Solved".to_string()
+    };
+    recorder.frame(&board, &result);
+
+    let lines = |frame: &String| frame.lines().count();
+    let width = recorder
+        .frames
+        .iter()
+        .flat_map(|frame| frame.lines())
+        .map(|line| line.chars().count())
+        .max()
+        .unwrap_or(0);
+    let height = recorder.frames.iter().map(lines).max().unwrap_or(0);
+
+    let mut out = String::new();
+    writeln!(
+        out,
+        "{{\"version\": 2, \"width\": {}, \"height\": {}, \"title\": {}}}",
+        width,
+        height,
+        json_string(title)
+    )
+    .unwrap();
+    for (i, frame) in recorder.frames.iter().enumerate() {
+        // A terminal needs a carriage return as well to start the next line at the left
+        let text = format!("{}{}", CLEAR, frame.replace('\n', "\r\n"));
+        writeln!(
+            out,
+            "[{:.3}, \"o\", {}]",
+            i as f64 * delay,
+            json_string(&text)
+        )
+        .unwrap();
+    }
+    out
+}
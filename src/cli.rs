@@ -0,0 +1,1258 @@
+//! The `aquarium` command: reading its arguments and running the command they name.
+
+use maplit::hashmap;
+use std::time::Instant;
+
+use crate::*;
+
+fn print_legend() {
+    // todo
+}
+
+fn game() {
+    // let board = Board::make(3, 3);
+    // board.print0();
+
+    let mut board = Board::make_b0();
+    let board_solved = Board::make_b0_solved();
+    board.print_with(RenderOptions {
+        mode: RenderMode::Puzzle,
+        ..RenderOptions::default()
+    });
+    println!("Board is solved: {}", board.is_solved());
+    println!("\n");
+
+    //
+    // board.flood(0, 0);
+    // board.invalidate(0, 5);
+    let mut trace = Vec::new();
+    board.solve_traced(&mut trace);
+    for deduction in &trace {
+        println!("{}", deduction);
+    }
+    if !board.is_solved() {
+        let (found, stats) = search::search(&mut board);
+        println!("Search found a solution: {} ({:?})", found, stats);
+    }
+    println!("\n");
+    board.print();
+    println!("Board is solved: {}", board.is_solved());
+    println!("Water levels: {:?}", board.levels());
+
+    // println!("\n");
+    // board_solved.print();
+}
+
+/// Print a puzzle file's hints and layout as CSV, for a spreadsheet.
+/// The file must hold a single puzzle unless `--index` picks one.
+fn export_csv(args: &[String]) {
+    let path = args
+        .first()
+        .filter(|arg| !arg.starts_with("--"))
+        .or_usage("Usage: csv <file> [--index N]");
+    let path = std::path::Path::new(path);
+    let entry = read_or_exit(path, format::read_puzzle(path, index_arg(args)));
+    print!("{}", format::to_csv(&entry.board));
+}
+
+/// Insert or remove rows and columns of a puzzle, merge aquariums and split them, and print the
+/// result in the text format. Aquariums are named by their labels in the file.
+/// The edits are made in the order given, each numbering the lines as the previous one left them.
+/// Removing a line can leave the hints not adding up, which is reported for the user to fix.
+fn edit(args: &[String]) {
+    let usage = "Usage: edit <file> [--index N] \
+                 (insert-row|remove-row|insert-col|remove-col <n> \
+                 | merge <aquarium> <aquarium> | split-below|split-right <aquarium> <n>)...";
+    let path = args
+        .first()
+        .filter(|arg| !arg.starts_with("--"))
+        .or_usage(usage);
+    let path = std::path::Path::new(path);
+    let mut entry = read_or_exit(path, format::read_puzzle(path, index_arg(args)));
+    let board = &mut entry.board;
+    let mut args_iter = args[1..].iter();
+    while let Some(arg) = args_iter.next() {
+        if arg == "--index" {
+            args_iter.next();
+            continue;
+        }
+        let mut next = || args_iter.next().or_usage(usage).as_str();
+        let number = |text: &str| -> usize { text.parse().or_usage(usage) };
+        let aquarium = |board: &Board, label: &str| {
+            board.partition_by_label(label).unwrap_or_else(|| {
+                eprintln!("There is no aquarium {}", label);
+                std::process::exit(1);
+            })
+        };
+        let result = match arg.as_str() {
+            "merge" => {
+                let (a, b) = (next(), next());
+                board.merge_partitions(aquarium(board, a), aquarium(board, b))
+            }
+            "split-below" | "split-right" => {
+                let partition = aquarium(board, next());
+                let at = number(next());
+                let cut = if arg == "split-below" {
+                    Cut::Below(at)
+                } else {
+                    Cut::Right(at)
+                };
+                board.split_partition(partition, cut)
+            }
+            _ => {
+                let at = number(next());
+                let (len, edit): (usize, fn(&mut Board, usize)) = match arg.as_str() {
+                    "insert-row" => (board.height + 1, Board::insert_row),
+                    "remove-row" => (board.height, Board::remove_row),
+                    "insert-col" => (board.width + 1, Board::insert_col),
+                    "remove-col" => (board.width, Board::remove_col),
+                    _ => usage_error(usage),
+                };
+                if at >= len || (arg.starts_with("remove") && len == 1) {
+                    Err(format!(
+                        "Can't {} {} of a {}x{} board",
+                        arg, at, board.width, board.height
+                    ))
+                } else {
+                    edit(board, at);
+                    Ok(())
+                }
+            }
+        };
+        if let Err(err) = result {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }
+    }
+    let row_total: isize = board.row_hints.iter().sum();
+    let col_total: isize = board.col_hints.iter().sum();
+    if row_total != col_total {
+        eprintln!(
+            "The row hints now add up to {} and the column hints to {}; \
+             fix them before the puzzle can be read back",
+            row_total, col_total
+        );
+    }
+    print!("{}", format::to_text(&entry));
+}
+
+/// Print the aquariums of a puzzle file and which of them touch as a Graphviz DOT graph.
+/// The file must hold a single puzzle unless `--index` picks one.
+fn export_dot(args: &[String]) {
+    let path = args
+        .first()
+        .filter(|arg| !arg.starts_with("--"))
+        .or_usage("Usage: dot <file> [--index N]");
+    let path = std::path::Path::new(path);
+    let entry = read_or_exit(path, format::read_puzzle(path, index_arg(args)));
+    print!("{}", dot::graph(&entry.name, &entry.board));
+}
+
+/// Record solving a puzzle file, or the built-in puzzle, as an asciinema cast to play back on the
+/// web. `--delay <ms>` sets the time between frames, 500 ms by default.
+fn export_cast(args: &[String]) {
+    let usage = "Usage: cast <out.cast> [file] [--index N] [--delay ms]";
+    let mut out = None;
+    let mut path = None;
+    let mut index = None;
+    let mut delay = 500;
+    let mut args_iter = args.iter();
+    while let Some(arg) = args_iter.next() {
+        match arg.as_str() {
+            "--index" => index = Some(args_iter.next().or_usage(usage).parse().or_usage(usage)),
+            "--delay" => delay = args_iter.next().or_usage(usage).parse().or_usage(usage),
+            _ if out.is_none() => out = Some(arg),
+            _ if path.is_none() => path = Some(arg),
+            _ => usage_error(usage),
+        }
+    }
+    let out = out.or_usage(usage);
+    let (name, board) = match path {
+        Some(path) => {
+            let path = std::path::Path::new(path);
+            let entry = read_or_exit(path, format::read_puzzle(path, index));
+            (entry.name, entry.board)
+        }
+        None => ("b0".to_string(), Board::make_b0()),
+    };
+    let text = cast::record(&name, &board, f64::from(delay) / 1000.0);
+    std::fs::write(out, text).expect("Could not write cast file");
+}
+
+/// Write solving a puzzle file, or the built-in puzzle, as numbered SVG frames in a directory:
+/// `frame-001.svg` is the puzzle, then a frame follows each deduction, or each pass over the rules
+/// with `--per iteration`.
+fn export_svg_frames(args: &[String]) {
+    let usage = "Usage: svg <out dir> [file] [--index N] [--per deduction|iteration]";
+    let mut out = None;
+    let mut path = None;
+    let mut index = None;
+    let mut step = svg::Step::Deduction;
+    let mut args_iter = args.iter();
+    while let Some(arg) = args_iter.next() {
+        match arg.as_str() {
+            "--index" => index = Some(args_iter.next().or_usage(usage).parse().or_usage(usage)),
+            "--per" => {
+                step = svg::Step::from_name(args_iter.next().or_usage(usage))
+                    .or_usage("Unknown step; expected deduction or iteration")
+            }
+            _ if out.is_none() => out = Some(arg),
+            _ if path.is_none() => path = Some(arg),
+            _ => usage_error(usage),
+        }
+    }
+    let out = std::path::Path::new(out.or_usage(usage));
+    let board = match path {
+        Some(path) => {
+            let path = std::path::Path::new(path);
+            read_or_exit(path, format::read_puzzle(path, index)).board
+        }
+        None => Board::make_b0(),
+    };
+    let frames = svg::frames(&board, step);
+    std::fs::create_dir_all(out).expect("Could not create the output directory");
+    let digits = frames.len().to_string().len().max(3);
+    for (i, frame) in frames.iter().enumerate() {
+        let name = format!("frame-{:01$}.svg", i + 1, digits);
+        std::fs::write(out.join(name), frame).expect("Could not write svg file");
+    }
+    println!("Wrote {} frames to {}", frames.len(), out.display());
+}
+
+/// Read a puzzle painted as a PNG and print it in the text format. The hints are read from
+/// `--hints <file>`, or from the sidecar file next to the image.
+fn import_image(args: &[String]) {
+    let usage = "Usage: image <png> [--hints <file>]";
+    let mut path = None;
+    let mut hints = None;
+    let mut args_iter = args.iter();
+    while let Some(arg) = args_iter.next() {
+        match arg.as_str() {
+            "--hints" => hints = Some(std::path::Path::new(args_iter.next().or_usage(usage))),
+            _ => path = Some(arg),
+        }
+    }
+    let path = std::path::Path::new(path.or_usage(usage));
+    let entry = read_or_exit(path, image::read(path, hints));
+    print!("{}", format::to_text(&entry));
+}
+
+/// Pack the puzzles of any number of puzzle files into one compact binary file
+fn pack(args: &[String]) {
+    let usage = "Usage: pack <out.aqb> <puzzle file>...";
+    let out_path = args.first().or_usage(usage);
+    let mut entries = Vec::new();
+    for path in &args[1..] {
+        let path = std::path::Path::new(path);
+        entries.extend(read_or_exit(path, format::read_file(path)));
+    }
+    let boards: Vec<_> = entries.iter().map(|entry| &entry.board).collect();
+    let bytes = binary::write(&boards).unwrap_or_else(|err| {
+        eprintln!("Could not pack the puzzles: {}", err);
+        std::process::exit(1);
+    });
+    std::fs::write(out_path, &bytes).expect("Could not write binary file");
+    println!("Packed {} puzzles into {} bytes", boards.len(), bytes.len());
+}
+
+/// Convert a puzzle file from one format to another. `--from` names the input's format and
+/// `--format` the output's; either can be `auto`, the default, to go by the file's contents or
+/// extension.
+fn convert(args: &[String]) {
+    let usage = "Usage: convert <input> <output> [--from auto|<format>] [--format auto|<format>]";
+    let mut paths = Vec::new();
+    let mut from = None;
+    let mut to = None;
+    let mut args_iter = args.iter();
+    while let Some(arg) = args_iter.next() {
+        let format_arg = |value: Option<&String>| {
+            formats::from_arg(value.or_usage(usage)).unwrap_or_else(|err| usage_error(&err))
+        };
+        match arg.as_str() {
+            "--from" => from = format_arg(args_iter.next()),
+            "--format" => to = format_arg(args_iter.next()),
+            _ => paths.push(std::path::Path::new(arg)),
+        }
+    }
+    let [input, output] = paths[..] else {
+        usage_error(usage)
+    };
+    let entries = read_or_exit(input, format::read_file_as(input, from));
+    let to = to
+        .or_else(|| formats::for_extension(output))
+        .or_usage("Can't tell the output's format from its extension; name it with --format");
+    let bytes = to.emit(&entries).unwrap_or_else(|err| {
+        eprintln!("Could not convert the puzzles: {}", err);
+        std::process::exit(1);
+    });
+    if let Err(err) = std::fs::write(output, bytes) {
+        eprintln!("Could not write {}: {}", output.display(), err);
+        std::process::exit(1);
+    }
+    println!(
+        "Wrote {} puzzles to {} as {}",
+        entries.len(),
+        output.display(),
+        to.name()
+    );
+}
+
+/// Save the rules' trace on every puzzle in a file, or on the built-in puzzles, with `record`,
+/// or check it against a saved trace with `compare`, printing where each puzzle first differs.
+/// Exits with an error status if any does.
+fn golden_trace(args: &[String]) {
+    let usage = "Usage: golden record|compare <trace file> [puzzle file]";
+    let mode = args.first().or_usage(usage);
+    let trace_path = std::path::Path::new(args.get(1).or_usage(usage));
+    let entries = match args.get(2) {
+        Some(path) => {
+            let path = std::path::Path::new(path);
+            read_or_exit(path, format::read_file(path))
+        }
+        None => builtin_puzzles(),
+    };
+    let traces = golden::record(&entries);
+
+    match mode.as_str() {
+        "record" => {
+            golden::write(trace_path, &traces).expect("Could not write trace file");
+            println!("Saved the traces of {} puzzles", traces.len());
+        }
+        "compare" => {
+            let saved = read_or_exit(trace_path, golden::read(trace_path));
+            let divergences = golden::compare(&saved, &traces);
+            for divergence in &divergences {
+                println!("{}", divergence);
+            }
+            if !divergences.is_empty() {
+                std::process::exit(1);
+            }
+            println!("All {} traces match", traces.len());
+        }
+        _ => usage_error(usage),
+    }
+}
+
+/// Solve a puzzle file, or the built-in puzzle, and print one line per puzzle giving whether it
+/// was solved, how long it took and how many deductions each rule made, followed by the final
+/// board. Every puzzle in the file is solved unless `--index` picks one.
+/// `--max-iterations <n>` caps the rules' passes, reporting what they were doing if they hit it.
+/// `--rules r1,r2,...` runs only the rules listed, leaving the rest to the search.
+fn summary(args: &[String]) {
+    let entries = match args.first().filter(|arg| !arg.starts_with("--")) {
+        Some(path) => {
+            let path = std::path::Path::new(path);
+            read_or_exit(
+                path,
+                match index_arg(args) {
+                    Some(index) => format::read_puzzle(path, Some(index)).map(|entry| vec![entry]),
+                    None => format::read_file(path),
+                },
+            )
+        }
+        None => builtin_puzzles(),
+    };
+    let max_iterations = args
+        .iter()
+        .position(|arg| arg == "--max-iterations")
+        .map(|i| {
+            let usage = "--max-iterations needs a number";
+            args.get(i + 1).or_usage(usage).parse().or_usage(usage)
+        });
+    let disabled_rules: Vec<_> = match args.iter().position(|arg| arg == "--rules") {
+        Some(i) => {
+            let names = args.get(i + 1).expect("--rules needs a list like r1,r2,r5");
+            let enabled: Vec<_> = names
+                .split(',')
+                .map(|name| Rule::from_name(name.trim()).or_usage("Unknown rule"))
+                .collect();
+            Rule::ALL
+                .iter()
+                .copied()
+                .filter(|rule| !enabled.contains(rule))
+                .collect()
+        }
+        None => Vec::new(),
+    };
+    for mut entry in entries {
+        entry.board.max_iterations = max_iterations;
+        entry.board.disabled_rules = disabled_rules.clone();
+        let start = std::time::Instant::now();
+        let mut trace = Vec::new();
+        let stats = entry.board.solve_traced(&mut trace);
+        let mut guesses = 0;
+        if !entry.board.is_solved() && !stats.stagnated {
+            guesses = search::search(&mut entry.board).1.guesses;
+        }
+        let elapsed = start.elapsed();
+
+        let counts: Vec<_> = Rule::ALL
+            .iter()
+            .map(|&rule| {
+                let count = trace
+                    .iter()
+                    .filter(|deduction| deduction.rule == rule)
+                    .count();
+                format!("{:?} {}", rule, count)
+            })
+            .collect();
+        println!(
+            "{}: {} in {:.3} ms; {}, {} guesses",
+            entry.name,
+            if entry.board.is_solved() {
+                "solved"
+            } else if stats.stagnated {
+                "stagnated"
+            } else {
+                "stalled"
+            },
+            elapsed.as_secs_f64() * 1000.0,
+            counts.join(", "),
+            guesses
+        );
+        entry.board.print();
+    }
+}
+
+fn idk() {
+    // let width = 3;
+    // let char_a = 'a';
+    // let char_pound = '#';
+    // println!("|{:2$>1$}|", char_pound, width, char_a);
+    let a = -1;
+    let b = 1;
+    let c = 10;
+
+    // let FORMAT = "{:>2}";
+
+    // println!(format!("|{}|", FORMAT), a);
+    // println!("|{:>2}|", b);
+    // println!("|{:>2}|", c);
+
+    // let n: usize = 11;
+    // // let n: usize = 3;
+
+    // let mut board = Board::make(n, n);
+    // for hint in board.row_hints.iter_mut() {
+    //     *hint = n.try_into().unwrap();
+    // }
+    // for hint in board.col_hints.iter_mut() {
+    //     *hint = n.try_into().unwrap();
+    // }
+    // board.print();
+
+    let maps = hashmap! {
+        3 => hashmap!{CellState::Empty => 3},
+        5 => hashmap!{CellState::Empty => 5} ,
+        7 => hashmap!{CellState::Empty => 7} ,
+    };
+
+    println!("{:#?}", maps);
+    let part = 3;
+
+    let other: isize = maps
+        .iter()
+        .filter_map(|it| {
+            if *it.0 != part {
+                Some(it.1.get(&CellState::Empty).unwrap_or(&0))
+            } else {
+                None
+            }
+        })
+        .sum();
+
+    for it in maps.iter() {
+        println!("{:?}", it);
+    }
+    println!("Other: {}", other);
+}
+
+/// Every puzzle compiled into the binary
+pub(crate) fn builtin_puzzles() -> Vec<stats::BatchEntry> {
+    vec![stats::BatchEntry {
+        name: "b0".to_string(),
+        difficulty: "easy".to_string(),
+        metadata: stats::Metadata {
+            source: "puzzle-aquarium.com".to_string(),
+            id: "3,095,209".to_string(),
+            ..Default::default()
+        },
+        board: Board::make_b0(),
+    }]
+}
+
+/// Solve every puzzle in a file or under a directory, or the built-in puzzles without one, and write
+/// the statistics as CSV, to `--csv <path>` or to stdout. Only the CSV goes to stdout.
+fn batch(args: &[String]) {
+    let usage = "Usage: batch [<dir|file>] [--csv <out>]";
+    let mut input = None;
+    let mut csv_path = None;
+    let mut args_iter = args.iter();
+    while let Some(arg) = args_iter.next() {
+        match arg.as_str() {
+            "--csv" => csv_path = Some(args_iter.next().or_usage(usage)),
+            _ if input.is_none() => input = Some(std::path::Path::new(arg)),
+            _ => usage_error(usage),
+        }
+    }
+    let entries = match input {
+        Some(path) => read_corpus(path),
+        None => builtin_puzzles(),
+    };
+    let reports = stats::run_batch(entries);
+    match csv_path {
+        Some(path) => {
+            let file = std::fs::File::create(path).expect("Could not create csv file");
+            stats::write_csv(file, &reports).expect("Could not write csv file");
+        }
+        None => {
+            stats::write_csv(std::io::stdout(), &reports).expect("Could not write csv");
+        }
+    }
+}
+
+/// Print a puzzle file, or the built-in puzzle, in the named render mode, working state by default.
+/// `--partitions` and `--index` add partition labels and row and column numbers, and `--ascii`
+/// keeps the output to printable 7-bit ASCII for terminals and logs that can't show anything else.
+/// `--linear` describes the board row by row in sentences instead, for screen readers.
+/// A board too wide for the terminal, or for `--width <n>` characters, is drawn with a character
+/// per cell, and in bands of columns if it's still too wide.
+fn show(args: &[String]) {
+    let usage =
+        "Usage: show [file] [mode] [--partitions] [--index] [--ascii] [--linear] [--width N]";
+    let mut options = RenderOptions::default();
+    let mut path = None;
+    let mut args_iter = args.iter();
+    while let Some(arg) = args_iter.next() {
+        match arg.as_str() {
+            "--width" => {
+                options.max_width = Some(args_iter.next().or_usage(usage).parse().or_usage(usage))
+            }
+            "--partitions" => options.show_partitions = true,
+            "--index" => options.show_index = true,
+            "--ascii" => options.ascii = true,
+            "--linear" => options.linear = true,
+            name => match RenderMode::from_name(name) {
+                Some(mode) => options.mode = mode,
+                None => path = Some(name),
+            },
+        }
+    }
+    let board = match path {
+        Some(path) => {
+            let path = std::path::Path::new(path);
+            read_or_exit(path, format::read_puzzle(path, None)).board
+        }
+        None => Board::make_b0(),
+    };
+    board.print_with(options);
+}
+
+/// Write every puzzle in the files and directories given, or the built-in puzzles without any,
+/// blank, to a PDF for printing. The page size is A4 unless `--page letter` is given.
+fn export_pdf(args: &[String]) {
+    let usage = "Usage: pdf <out.pdf> [files or dirs...] [--page a4|letter]";
+    let mut out = None;
+    let mut paths = Vec::new();
+    let mut page_size = pdf::PageSize::A4;
+    let mut args_iter = args.iter();
+    while let Some(arg) = args_iter.next() {
+        match arg.as_str() {
+            "--page" => {
+                page_size = pdf::PageSize::from_name(args_iter.next().or_usage(usage))
+                    .or_usage("Unknown page size")
+            }
+            path if out.is_none() => out = Some(path),
+            path => paths.push(std::path::Path::new(path)),
+        }
+    }
+    let pdf_path = out.or_usage(usage);
+    let entries = if paths.is_empty() {
+        builtin_puzzles()
+    } else {
+        paths.into_iter().flat_map(read_corpus).collect()
+    };
+    let puzzles: Vec<_> = entries
+        .iter()
+        .map(|entry| (entry.name.as_str(), &entry.board))
+        .collect();
+    let bytes = pdf::puzzles_pdf(&puzzles, page_size);
+    std::fs::write(pdf_path, bytes).expect("Could not write pdf file");
+}
+
+/// Write a booklet of puzzles to print: every puzzle in the files given, or with `--generate <n>`
+/// that many new puzzles with a single solution, `--size` cells square (6 by default) from
+/// `--seed` (1 by default). `--per-page` puts 1, 2, 4 or 6 puzzles on a page, 4 by default, and
+/// the solutions follow at the back. The page size is A4 unless `--page letter` is given.
+fn booklet(args: &[String]) {
+    let usage = "Usage: booklet <out.pdf> [files...] [--generate N [--size N] [--seed N]] \
+                 [--per-page 1|2|4|6] [--page a4|letter]";
+    let mut out = None;
+    let mut files = Vec::new();
+    let mut generate_count = 0;
+    let mut size = 6;
+    let mut seed = 1;
+    let mut per_page = 4;
+    let mut page_size = pdf::PageSize::A4;
+    let mut args_iter = args.iter();
+    while let Some(arg) = args_iter.next() {
+        match arg.as_str() {
+            "--generate" => {
+                generate_count = args_iter.next().or_usage(usage).parse().or_usage(usage)
+            }
+            "--size" => size = args_iter.next().or_usage(usage).parse().or_usage(usage),
+            "--seed" => seed = args_iter.next().or_usage(usage).parse().or_usage(usage),
+            "--per-page" => per_page = args_iter.next().or_usage(usage).parse().or_usage(usage),
+            "--page" => {
+                page_size = pdf::PageSize::from_name(args_iter.next().or_usage(usage))
+                    .or_usage("Unknown page size")
+            }
+            path if out.is_none() => out = Some(path),
+            path => files.push(path),
+        }
+    }
+    let out = out.or_usage(usage);
+    if ![1, 2, 4, 6].contains(&per_page) || size == 0 {
+        usage_error(usage);
+    }
+
+    let mut entries = Vec::new();
+    for path in files {
+        let path = std::path::Path::new(path);
+        entries.extend(read_or_exit(path, format::read_file(path)));
+    }
+    let mut rng = generate::Rng::new(seed);
+    let mut attempts = 0;
+    while entries.len() < generate_count && attempts < 1000 * generate_count {
+        attempts += 1;
+        let mut board = generate::generate(size, size, &mut rng);
+        board.clear();
+        if search::is_unique(&board) {
+            entries.push(stats::BatchEntry {
+                name: format!("{}x{} #{}", size, size, entries.len() + 1),
+                difficulty: String::new(),
+                metadata: stats::Metadata::default(),
+                board,
+            });
+        }
+    }
+    if entries.is_empty() {
+        eprintln!("No puzzles to print");
+        std::process::exit(1);
+    }
+
+    let solutions: Vec<_> = entries
+        .iter()
+        .map(|entry| {
+            let mut board = entry.board.clone();
+            board.clear();
+            search::solve(&mut board);
+            if board.is_solved() {
+                Some(board)
+            } else {
+                eprintln!("{} has no solution", entry.name);
+                None
+            }
+        })
+        .collect();
+    let puzzles: Vec<_> = entries
+        .iter()
+        .map(|entry| (entry.name.as_str(), &entry.board))
+        .collect();
+    let bytes = pdf::booklet_pdf(&puzzles, &solutions, page_size, per_page);
+    std::fs::write(out, bytes).expect("Could not write pdf file");
+}
+
+/// Solve every puzzle file under the directory, or every puzzle in a corpus file, and print a
+/// summary table, or with `--jsonl` a line of JSON per puzzle as each one finishes. Puzzles are
+/// read one at a time, so with `--jsonl` a corpus of any size can be run.
+/// `--timeout <ms>` gives up on any puzzle taking longer.
+/// Exits with an error status if any puzzle was not solved.
+fn run_dir(args: &[String]) {
+    let usage = "Usage: run <dir|file> [--jsonl] [--timeout <ms>]";
+    let mut path = None;
+    let mut jsonl = false;
+    let mut timeout = None;
+    let mut args_iter = args.iter();
+    while let Some(arg) = args_iter.next() {
+        match arg.as_str() {
+            "--jsonl" => jsonl = true,
+            "--timeout" => {
+                let ms = args_iter.next().or_usage(usage).parse().or_usage(usage);
+                timeout = Some(std::time::Duration::from_millis(ms));
+            }
+            _ => path = Some(arg),
+        }
+    }
+    let path = path.or_usage(usage);
+    let mut outcomes = Vec::new();
+    let mut all_solved = true;
+    campaign::run(std::path::Path::new(path), timeout, |outcome| {
+        all_solved &= outcome.status == campaign::Status::Solved;
+        if jsonl {
+            campaign::write_json_line(std::io::stdout(), &outcome).expect("Could not write json");
+        } else {
+            outcomes.push(outcome);
+        }
+    })
+    .expect("Could not read puzzles");
+    if !jsonl {
+        campaign::write_summary(std::io::stdout(), &outcomes).expect("Could not write summary");
+    }
+    if !all_solved {
+        std::process::exit(1);
+    }
+}
+
+/// Solve the puzzles in a file, or under a directory, with two solver configurations and compare
+/// their times and steps; see the `bench` module for how a configuration is written.
+/// `--repeat <n>` solves each puzzle n times with each, keeping the fastest, 3 by default.
+fn benchmark(args: &[String]) {
+    let usage = "Usage: bench <dir|file> <config A> <config B> [--repeat N]";
+    let mut positional = Vec::new();
+    let mut repeat = 3;
+    let mut args_iter = args.iter();
+    while let Some(arg) = args_iter.next() {
+        match arg.as_str() {
+            "--repeat" => repeat = args_iter.next().or_usage(usage).parse().or_usage(usage),
+            _ => positional.push(arg),
+        }
+    }
+    let [path, a, b] = positional[..] else {
+        usage_error(usage);
+    };
+    let config = |text: &str| {
+        bench::Config::parse(text).unwrap_or_else(|err| {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        })
+    };
+    let (a, b) = (config(a), config(b));
+
+    let entries = read_corpus(std::path::Path::new(path));
+    let comparisons = bench::run(&entries, &a, &b, repeat);
+    bench::write_report(std::io::stdout(), &a, &b, &comparisons).expect("Could not write report");
+}
+
+/// Solve every puzzle file under a directory, or every puzzle in a corpus file, with the rules
+/// alone and report how many needed the search to finish, by size and by declared difficulty,
+/// and which ones.
+fn survey_dir(path: Option<&String>) {
+    let path = std::path::Path::new(path.or_usage("Usage: survey <dir|file>"));
+    let entries = survey::run(path).expect("Could not read puzzles");
+    survey::write_report(std::io::stdout(), &entries).expect("Could not write report");
+}
+
+/// Print `message`, a command's usage or what was wrong with one of its arguments, to stderr and
+/// exit, rather than panicking over a mistyped command line
+fn usage_error(message: &str) -> ! {
+    eprintln!("{}", message);
+    std::process::exit(1);
+}
+
+/// `usage_error` for a missing or malformed argument
+trait OrUsage<T> {
+    fn or_usage(self, message: &str) -> T;
+}
+
+impl<T> OrUsage<T> for Option<T> {
+    fn or_usage(self, message: &str) -> T {
+        self.unwrap_or_else(|| usage_error(message))
+    }
+}
+
+impl<T, E> OrUsage<T> for Result<T, E> {
+    fn or_usage(self, message: &str) -> T {
+        self.unwrap_or_else(|_| usage_error(message))
+    }
+}
+
+/// The value of `--index`, the position of a puzzle in a file holding several, counting from 1
+fn index_arg(args: &[String]) -> Option<usize> {
+    let i = args.iter().position(|arg| arg == "--index")?;
+    let index = args.get(i + 1).or_usage("--index needs a number");
+    Some(index.parse().or_usage("--index needs a number"))
+}
+
+/// Every puzzle in a file, or in the puzzle files under a directory. A file under the directory
+/// that can't be read is skipped with a note on stderr; a lone file that can't be read ends the run.
+fn read_corpus(path: &std::path::Path) -> Vec<stats::BatchEntry> {
+    if !path.is_dir() {
+        return read_or_exit(path, format::read_file(path));
+    }
+    let mut entries = Vec::new();
+    for file in campaign::puzzle_files(path).expect("Could not read directory") {
+        match format::read_file(&file) {
+            Ok(read) => entries.extend(read),
+            Err(err) => eprintln!("Skipping {}: {}", file.display(), err),
+        }
+    }
+    entries
+}
+
+/// The puzzles read from `path`, or exit after printing why they couldn't be read. The error is
+/// printed as is, since it may quote the bad line.
+fn read_or_exit<T>(path: &std::path::Path, read: Result<T, String>) -> T {
+    read.unwrap_or_else(|err| {
+        eprintln!("Could not read {}: {}", path.display(), err);
+        std::process::exit(1);
+    })
+}
+
+/// Solve a puzzle file and print a JavaScript snippet that fills the solution into the puzzle's
+/// page when pasted into the browser console, with `--cells` the flooded cells as `ix,iy` lines, or
+/// with `--levels` the depth of each aquarium's water as a `levels:` line of the text format.
+/// `--selector <css>` picks the page's cell elements.
+fn fill(args: &[String]) {
+    let usage = "Usage: fill <file> [--index N] [--cells | --levels] [--selector <css>]";
+    let mut path = None;
+    let mut cells = false;
+    let mut levels = false;
+    let mut selector = autofill::DEFAULT_SELECTOR.to_string();
+    let mut args_iter = args.iter();
+    while let Some(arg) = args_iter.next() {
+        match arg.as_str() {
+            "--cells" => cells = true,
+            "--levels" => levels = true,
+            "--selector" => selector = args_iter.next().or_usage(usage).clone(),
+            "--index" => {
+                args_iter.next();
+            }
+            _ => path = Some(arg),
+        }
+    }
+    let path = std::path::Path::new(path.or_usage(usage));
+
+    let mut entry = read_or_exit(path, format::read_puzzle(path, index_arg(args)));
+    let puzzle = entry.board.clone();
+    search::solve(&mut entry.board);
+    if !entry.board.is_solved() {
+        eprintln!("{} has no solution", entry.name);
+        if let Some(conflict) = unsat::conflicting_hints(&puzzle) {
+            eprintln!("{}", conflict.describe(&puzzle));
+        }
+        std::process::exit(1);
+    }
+    if cells {
+        for (ix, iy) in autofill::flooded_cells(&entry.board) {
+            println!("{},{}", ix, iy);
+        }
+    } else if levels {
+        print!("{}", format::to_levels(&entry.board).unwrap());
+    } else {
+        print!("{}", autofill::snippet(&entry.board, &selector));
+    }
+}
+
+/// Check every deduction the rules make against a known solution and report the first one
+/// that contradicts it. The solution to a puzzle file is found by search alone, without the rules;
+/// without a file the built-in puzzle and its solution are used.
+/// Every puzzle in the file is checked unless `--index` picks one.
+fn check_oracle(args: &[String]) {
+    let puzzles = match args.first().filter(|arg| !arg.starts_with("--")) {
+        Some(path) => {
+            let path = std::path::Path::new(path);
+            let entries = read_or_exit(
+                path,
+                match index_arg(args) {
+                    Some(index) => format::read_puzzle(path, Some(index)).map(|entry| vec![entry]),
+                    None => format::read_file(path),
+                },
+            );
+            entries
+                .into_iter()
+                .filter_map(|entry| {
+                    let mut solution = entry.board.clone();
+                    let (found, _) = search::search(&mut solution);
+                    if !found {
+                        eprintln!("{} has no solution to check against", entry.name);
+                        if let Some(conflict) = unsat::conflicting_hints(&entry.board) {
+                            eprintln!("{}", conflict.describe(&entry.board));
+                        }
+                        return None;
+                    }
+                    Some((entry.name, entry.board, solution))
+                })
+                .collect()
+        }
+        None => vec![("b0".to_string(), Board::make_b0(), Board::make_b0_solved())],
+    };
+    for (name, board, solution) in puzzles {
+        check_against(&name, &board, &solution);
+    }
+}
+
+fn check_against(name: &str, board: &Board, solution: &Board) {
+    let (stats, contradiction) = oracle::check(board, solution).expect("Bad solution");
+    match contradiction {
+        None => println!(
+            "{}: all {} deductions agree with the solution",
+            name, stats.deductions
+        ),
+        Some(contradiction) => {
+            let deduction = &contradiction.deduction;
+            println!(
+                "{}: deduction {} (pass {}) contradicts the solution: {}",
+                name,
+                contradiction.index + 1,
+                deduction.iteration,
+                deduction
+            );
+            println!("{}", deduction.reason);
+            let cells: Vec<_> = contradiction
+                .cells
+                .iter()
+                .map(|(ix, iy)| format!("({}, {})", ix, iy))
+                .collect();
+            println!("Wrong cells: {}", cells.join(" "));
+            println!("\nBefore the deduction:");
+            contradiction.before.print();
+            println!("\nSolution:");
+            solution.print();
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Write a markdown walkthrough of the built-in puzzle,
+/// either to the given path or to stdout
+fn walkthrough(md_path: Option<&String>) {
+    let text = markdown::walkthrough("Aquarium b0", &Board::make_b0());
+    match md_path {
+        Some(path) => std::fs::write(path, text).expect("Could not write markdown file"),
+        None => print!("{}", text),
+    }
+}
+
+/// Solve the built-in puzzle, naming the technique behind each deduction.
+/// Each technique is described the first time it comes up.
+fn teach() {
+    let mut board = Board::make_b0();
+    board.print();
+    println!();
+
+    let mut trace = Vec::new();
+    board.solve_traced(&mut trace);
+
+    let mut seen = Vec::new();
+    for deduction in &trace {
+        println!("{} -- {}", deduction, deduction.technique.name());
+        println!("    {}", deduction.reason);
+        if !seen.contains(&deduction.technique) {
+            seen.push(deduction.technique);
+            println!(
+                "    {}: {}",
+                deduction.technique.name(),
+                deduction.technique.description()
+            );
+        }
+    }
+
+    println!();
+    board.print();
+    println!("Board is solved: {}", board.is_solved());
+}
+
+/// Suggest the next deduction for a puzzle file, or the built-in puzzle, from the cells it gives.
+/// `--rank cells` suggests the one deciding the most cells and `--rank unlocks` the one opening up
+/// the most new deductions, rather than the first the solver would make. `--all` lists every
+/// deduction available, best first.
+fn suggest_hint(args: &[String]) {
+    let usage = "Usage: hint [file] [--index N] [--rank first|cells|unlocks] [--all]";
+    let board = match args.first().filter(|arg| !arg.starts_with("--")) {
+        Some(path) => {
+            let path = std::path::Path::new(path);
+            read_or_exit(path, format::read_puzzle(path, index_arg(args))).board
+        }
+        None => Board::make_b0(),
+    };
+    let rank = match args.iter().position(|arg| arg == "--rank") {
+        Some(i) => hint::Rank::from_name(args.get(i + 1).or_usage(usage)).or_usage(usage),
+        None => hint::Rank::First,
+    };
+    let hints = hint::hints(&board, rank);
+    if hints.is_empty() {
+        println!("The rules can't go any further from here");
+        return;
+    }
+    let n_shown = if args.iter().any(|arg| arg == "--all") {
+        hints.len()
+    } else {
+        1
+    };
+    for hint in &hints[..n_shown] {
+        let deduction = &hint.deduction;
+        println!(
+            "{} ({}): decides {} cells, opens up {} new deductions",
+            deduction,
+            deduction.technique.name(),
+            hint.cells,
+            hint.unlocks
+        );
+        println!("    {}", deduction.reason);
+    }
+}
+
+/// Tile the solved built-in puzzle into an n x n grid and try to solve the result
+fn compose_tiles(n: Option<&String>) {
+    let n: usize = n.map_or(2, |n| n.parse().expect("Tile count must be a number"));
+    let tiles: Vec<Vec<_>> = (0..n)
+        .map(|_| (0..n).map(|_| Board::make_b0_solved()).collect())
+        .collect();
+
+    let mut board = compose::compose(&tiles).expect("Could not compose tiles");
+    board.clear();
+    board.print();
+
+    let stats = board.solve();
+    println!();
+    board.print();
+    println!(
+        "Board is solved: {} ({} deductions)",
+        board.is_solved(),
+        stats.deductions
+    );
+}
+
+/// Solve the built-in puzzle by search alone, without the rules,
+/// with the named heuristic. Useful for comparing heuristics.
+fn search_only(heuristic: Option<&String>) {
+    let heuristic = match heuristic {
+        Some(name) => search::Heuristic::from_name(name).or_usage("Unknown heuristic"),
+        None => search::Heuristic::default(),
+    };
+    let mut board = Board::make_b0();
+    let (found, stats) = search::search_with(&mut board, heuristic);
+    board.print();
+    println!("Search with {:?} found a solution: {}", heuristic, found);
+    println!("{:?}", stats);
+}
+
+/// Count the solutions of the built-in puzzle laid next to its mirror image,
+/// with and without symmetry breaking
+fn count() {
+    let solved = Board::make_b0_solved();
+    let tiles = vec![vec![solved.clone(), solved.mirrored()]];
+    let mut board = compose::compose(&tiles).expect("Could not compose tiles");
+    board.clear();
+    board.print();
+
+    for &symmetry in &[false, true] {
+        let (count, stats) = search::count_solutions(&board, usize::MAX, symmetry);
+        println!(
+            "Symmetry breaking {}: {} solutions, {:?}",
+            symmetry, count, stats
+        );
+    }
+}
+
+/// Enumerate every layout and every puzzle with a unique solution on tiny boards, and print how
+/// many there are. Sizes are given as `WxH`, 3x3 by default; 4x4 takes a while, as every one of
+/// its 16 million sets of walls is tried. `--rules` also counts the puzzles the rules solve alone,
+/// which is much slower again.
+fn enumerate_boards(args: &[String]) {
+    let usage = "Usage: enumerate [WxH...] [--rules]";
+    let rules = args.iter().any(|arg| arg == "--rules");
+    let args: Vec<_> = args.iter().filter(|arg| *arg != "--rules").collect();
+    let sizes: Vec<_> = if args.is_empty() {
+        vec![(3, 3)]
+    } else {
+        args.iter()
+            .map(|arg| {
+                let (width, height) = arg.split_once('x').or_usage(usage);
+                (
+                    width.parse().or_usage(usage),
+                    height.parse().or_usage(usage),
+                )
+            })
+            .collect()
+    };
+    for (i, &(width, height)) in sizes.iter().enumerate() {
+        if i > 0 {
+            println!();
+        }
+        match enumerate::enumerate(width, height, rules) {
+            Ok(tallies) => {
+                enumerate::write_report(std::io::stdout(), width, height, &tallies, rules)
+                    .expect("Could not write the report")
+            }
+            Err(err) => {
+                eprintln!("{}", err);
+                std::process::exit(1);
+            }
+        }
+    }
+}
+
+/// Print the puzzle of the day in the text format: today's in UTC, or the one for `--date`.
+/// `--size <n>` picks its size, 6 by default, and `--difficulty easy|hard` whether the rules
+/// solve it alone, which they do by default.
+fn daily_puzzle(args: &[String]) {
+    let usage = "Usage: daily [--date YYYY-MM-DD] [--size N] [--difficulty easy|hard]";
+    let mut date = None;
+    let mut size = 6;
+    let mut difficulty = daily::Difficulty::Easy;
+    let mut args_iter = args.iter();
+    while let Some(arg) = args_iter.next() {
+        match arg.as_str() {
+            "--date" => {
+                let text = args_iter.next().or_usage(usage);
+                date = Some(daily::Date::parse(text).unwrap_or_else(|err| usage_error(&err)));
+            }
+            "--size" => size = args_iter.next().or_usage(usage).parse().or_usage(usage),
+            "--difficulty" => {
+                difficulty = daily::Difficulty::from_name(args_iter.next().or_usage(usage))
+                    .or_usage("Unknown difficulty; expected easy or hard")
+            }
+            _ => usage_error(usage),
+        }
+    }
+    if size == 0 {
+        usage_error(usage);
+    }
+    let date = date.unwrap_or_else(daily::Date::today);
+    match daily::puzzle(date, size, difficulty) {
+        Ok(entry) => print!("{}", format::to_text(&entry)),
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Feed random and deliberately broken puzzles through the readers, the rules and the search, and
+/// report any that panic, hang or draw an unsound deduction. Each failing case is shrunk and
+/// written to `--out`, `fuzz-failures` by default. Exits with an error status if any were found.
+fn fuzz_cases(args: &[String]) {
+    let usage = "Usage: fuzz [--iterations N] [--seed N] [--max-size N] [--timeout ms] [--out dir]";
+    let mut options = fuzz::Options {
+        iterations: 1000,
+        seed: 1,
+        max_size: 8,
+        timeout: std::time::Duration::from_secs(5),
+        out: std::path::PathBuf::from("fuzz-failures"),
+    };
+    let mut args_iter = args.iter();
+    while let Some(arg) = args_iter.next() {
+        let value = args_iter.next().or_usage(usage);
+        match arg.as_str() {
+            "--iterations" => options.iterations = value.parse().or_usage(usage),
+            "--seed" => options.seed = value.parse().or_usage(usage),
+            "--max-size" => options.max_size = value.parse().or_usage(usage),
+            "--timeout" => {
+                options.timeout = std::time::Duration::from_millis(value.parse().or_usage(usage))
+            }
+            "--out" => options.out = std::path::PathBuf::from(value),
+            _ => usage_error(usage),
+        }
+    }
+    if options.max_size == 0 {
+        usage_error(usage);
+    }
+    let summary = fuzz::run(&options).unwrap_or_else(|err| {
+        eprintln!("{}", err);
+        std::process::exit(1);
+    });
+    for found in &summary.found {
+        println!(
+            "case {}: {} ({})",
+            found.iteration,
+            found.failure,
+            found.path.display()
+        );
+    }
+    println!(
+        "{} cases, {} read as puzzles, {} failures",
+        summary.cases,
+        summary.parsed,
+        summary.found.len()
+    );
+    if !summary.found.is_empty() {
+        std::process::exit(1);
+    }
+}
+
+/// Generate large random puzzles and time solving each one.
+/// `stress [size] [--count N] [--seed N]` makes 100x100 puzzles from seed 1 by default.
+/// Exits with an error status if any puzzle was not solved.
+fn stress(args: &[String]) {
+    let usage = "Usage: stress [size] [--count N] [--seed N]";
+    let mut size = 100;
+    let mut count = 3;
+    let mut seed = 1;
+    let mut args_iter = args.iter();
+    while let Some(arg) = args_iter.next() {
+        match arg.as_str() {
+            "--count" => count = args_iter.next().or_usage(usage).parse().or_usage(usage),
+            "--seed" => seed = args_iter.next().or_usage(usage).parse().or_usage(usage),
+            size_arg => size = size_arg.parse().or_usage(usage),
+        }
+    }
+
+    let mut all_solved = true;
+    for seed in seed..seed + count {
+        let start = Instant::now();
+        let mut board = generate::generate(size, size, &mut generate::Rng::new(seed));
+        board.clear();
+        let generated = start.elapsed();
+
+        let stats = search::solve(&mut board);
+        let solved = board.is_solved();
+        all_solved &= solved;
+        println!(
+            "seed {}: {}x{}, {} aquariums, generated in {:.3} s, {} in {:.3} s \
+             ({} iterations, {} deductions, {} guesses)",
+            seed,
+            size,
+            size,
+            board.n_partitions,
+            generated.as_secs_f64(),
+            if solved { "solved" } else { "not solved" },
+            stats.elapsed.as_secs_f64(),
+            stats.iterations,
+            stats.deductions,
+            stats.guesses
+        );
+    }
+    if !all_solved {
+        std::process::exit(1);
+    }
+}
+
+/// Run the `aquarium` command with the process's arguments
+pub fn run() {
+    let args: Vec<String> = std::env::args().collect();
+    match args.get(1).map(|arg| arg.as_str()) {
+        Some("batch") => batch(&args[2..]),
+        Some("run") => run_dir(&args[2..]),
+        Some("survey") => survey_dir(args.get(2)),
+        Some("bench") => benchmark(&args[2..]),
+        Some("fill") => fill(&args[2..]),
+        Some("oracle") => check_oracle(&args[2..]),
+        Some("markdown") => walkthrough(args.get(2)),
+        Some("pdf") => export_pdf(&args[2..]),
+        Some("booklet") => booklet(&args[2..]),
+        Some("show") => show(&args[2..]),
+        Some("teach") => teach(),
+        Some("hint") => suggest_hint(&args[2..]),
+        Some("search") => search_only(args.get(2)),
+        Some("count") => count(),
+        Some("enumerate") => enumerate_boards(&args[2..]),
+        Some("stress") => stress(&args[2..]),
+        Some("fuzz") => fuzz_cases(&args[2..]),
+        Some("daily") => daily_puzzle(&args[2..]),
+        Some("compose") => compose_tiles(args.get(2)),
+        Some("golden") => golden_trace(&args[2..]),
+        Some("csv") => export_csv(&args[2..]),
+        Some("dot") => export_dot(&args[2..]),
+        Some("cast") => export_cast(&args[2..]),
+        Some("svg") => export_svg_frames(&args[2..]),
+        Some("image") => import_image(&args[2..]),
+        Some("edit") => edit(&args[2..]),
+        Some("pack") => pack(&args[2..]),
+        Some("convert") => convert(&args[2..]),
+        Some("--summary") => summary(&args[2..]),
+        _ => game(),
+    }
+    // idk();
+}
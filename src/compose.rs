@@ -0,0 +1,78 @@
+//! Build large boards out of smaller solved ones.
+
+use crate::Board;
+
+/// Tile solved boards into a single larger board.
+///
+/// `tiles` is a grid of boards, listed row by row. Boards in the same tile row must share a height
+/// and boards in the same tile column must share a width. Each tile keeps its own aquariums, renumbered
+/// so they stay distinct, and the hints of the composed board are the flooded counts of its solution.
+///
+/// The returned board holds the composed solution; call `clear` on it to get the puzzle.
+pub fn compose(tiles: &[Vec<Board>]) -> Result<Board, String> {
+    if tiles.is_empty() || tiles[0].is_empty() {
+        return Err("No tiles to compose".to_string());
+    }
+    let tile_cols = tiles[0].len();
+
+    for (ty, tile_row) in tiles.iter().enumerate() {
+        if tile_row.len() != tile_cols {
+            return Err(format!(
+                "Tile row {} has {} tiles, expected {}",
+                ty,
+                tile_row.len(),
+                tile_cols
+            ));
+        }
+        for (tx, tile) in tile_row.iter().enumerate() {
+            if tile.height != tile_row[0].height {
+                return Err(format!(
+                    "Tile ({}, {}) has height {}, expected {}",
+                    tx, ty, tile.height, tile_row[0].height
+                ));
+            }
+            if tile.width != tiles[0][tx].width {
+                return Err(format!(
+                    "Tile ({}, {}) has width {}, expected {}",
+                    tx, ty, tile.width, tiles[0][tx].width
+                ));
+            }
+            if !tile.is_solved() {
+                return Err(format!("Tile ({}, {}) is not solved", tx, ty));
+            }
+        }
+    }
+
+    let width: usize = tiles[0].iter().map(|tile| tile.width).sum();
+    let height: usize = tiles.iter().map(|tile_row| tile_row[0].height).sum();
+    let mut board = Board::make(width, height);
+
+    let mut y_offset = 0;
+    let mut partition_offset = 0;
+    for tile_row in tiles {
+        let mut x_offset = 0;
+        for tile in tile_row {
+            for iy in 0..tile.height {
+                for ix in 0..tile.width {
+                    let cell = tile.cell_at(ix, iy);
+                    let index = (y_offset + iy) * width + x_offset + ix;
+                    board.cells[index].partition = cell.partition + partition_offset;
+                    board.cells[index].state = cell.state;
+                }
+            }
+            partition_offset += tile.n_partitions;
+            x_offset += tile.width;
+        }
+        y_offset += tile_row[0].height;
+    }
+    board.index_partitions();
+
+    for iy in 0..height {
+        board.row_hints[iy] = board.flooded_rows.count(iy);
+    }
+    for ix in 0..width {
+        board.col_hints[ix] = board.flooded_cols.count(ix);
+    }
+
+    Ok(board)
+}
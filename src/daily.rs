@@ -0,0 +1,249 @@
+//! The puzzle of the day.
+//!
+//! The generator's seed is worked out from the date, the size and the difficulty alone, so
+//! everyone asking for the same day's puzzle gets the same one. Puzzles are drawn from the seeded
+//! generator until one has a single solution and is as hard as asked. Changing how the seed is
+//! derived or how the generator works changes every day's puzzle, so both are best left alone.
+
+use std::fmt;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::generate::{self, Rng};
+use crate::search;
+use crate::stats::{BatchEntry, Metadata};
+
+/// How many puzzles are drawn looking for a suitable one before giving up
+const MAX_ATTEMPTS: usize = 10_000;
+
+/// A day in the proleptic Gregorian calendar
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub struct Date {
+    pub year: i64,
+    pub month: u32,
+    pub day: u32,
+}
+
+impl Date {
+    /// Today in UTC, so the day turns over at the same moment everywhere
+    pub fn today() -> Date {
+        let seconds = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("The clock is set before 1970")
+            .as_secs();
+        Date::from_days((seconds / 86_400) as i64)
+    }
+
+    /// Parse a date written `YYYY-MM-DD`
+    pub fn parse(text: &str) -> Result<Date, String> {
+        let bad = || format!("Bad date {}; expected YYYY-MM-DD", text);
+        let parts: Vec<_> = text.split('-').collect();
+        let [year, month, day] = parts[..] else {
+            return Err(bad());
+        };
+        let year: i64 = year.parse().map_err(|_| bad())?;
+        let month: u32 = month.parse().map_err(|_| bad())?;
+        let day: u32 = day.parse().map_err(|_| bad())?;
+        if !(1..=12).contains(&month) || day == 0 || day > days_in_month(year, month) {
+            return Err(bad());
+        }
+        Ok(Date { year, month, day })
+    }
+
+    /// Days since 1970-01-01
+    pub fn days(self) -> i64 {
+        // Counting years from March puts the leap day at the end of the year
+        let year = if self.month <= 2 {
+            self.year - 1
+        } else {
+            self.year
+        };
+        let era = year.div_euclid(400);
+        let year_of_era = year.rem_euclid(400);
+        let month = i64::from((self.month + 9) % 12);
+        let day_of_year = (153 * month + 2) / 5 + i64::from(self.day) - 1;
+        let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+        era * 146_097 + day_of_era - 719_468
+    }
+
+    /// The date `days` days after 1970-01-01
+    pub fn from_days(days: i64) -> Date {
+        let days = days + 719_468;
+        let era = days.div_euclid(146_097);
+        let day_of_era = days.rem_euclid(146_097);
+        let year_of_era =
+            (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let month = (5 * day_of_year + 2) / 153;
+        let day = (day_of_year - (153 * month + 2) / 5 + 1) as u32;
+        let month = if month < 10 { month + 3 } else { month - 9 } as u32;
+        let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+        Date { year, month, day }
+    }
+}
+
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+fn days_in_month(year: i64, month: u32) -> u32 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// How hard the day's puzzle is
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub enum Difficulty {
+    /// The rules solve it alone
+    Easy,
+    /// The rules stall and a guess is needed
+    Hard,
+}
+
+impl Difficulty {
+    pub fn from_name(name: &str) -> Option<Difficulty> {
+        match name {
+            "easy" => Some(Difficulty::Easy),
+            "hard" => Some(Difficulty::Hard),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Difficulty::Easy => "easy",
+            Difficulty::Hard => "hard",
+        }
+    }
+}
+
+/// The generator seed for a day's puzzle: the days since 1970 in the low 32 bits, the size in
+/// the next 16 and the difficulty above them
+pub fn seed(date: Date, size: usize, difficulty: Difficulty) -> u64 {
+    let difficulty = match difficulty {
+        Difficulty::Easy => 0,
+        Difficulty::Hard => 1,
+    };
+    (date.days() as u64 & 0xffff_ffff) | (size as u64 & 0xffff) << 32 | difficulty << 48
+}
+
+/// The puzzle of the day, `size` cells square
+pub fn puzzle(date: Date, size: usize, difficulty: Difficulty) -> Result<BatchEntry, String> {
+    let mut rng = Rng::new(seed(date, size, difficulty));
+    for _ in 0..MAX_ATTEMPTS {
+        let mut board = generate::generate(size, size, &mut rng);
+        board.clear();
+        if !search::is_unique(&board) {
+            continue;
+        }
+        let mut solved = board.clone();
+        solved.solve();
+        let found = if solved.is_solved() {
+            Difficulty::Easy
+        } else {
+            Difficulty::Hard
+        };
+        if found == difficulty {
+            return Ok(BatchEntry {
+                name: format!("daily {}", date),
+                difficulty: difficulty.name().to_string(),
+                metadata: Metadata {
+                    date: date.to_string(),
+                    ..Metadata::default()
+                },
+                board,
+            });
+        }
+    }
+    Err(format!(
+        "No {} {}x{} puzzle with a single solution turned up in {} tries",
+        difficulty.name(),
+        size,
+        size,
+        MAX_ATTEMPTS
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(year: i64, month: u32, day: u32) -> Date {
+        Date { year, month, day }
+    }
+
+    #[test]
+    fn known_days_since_1970() {
+        assert_eq!(date(1970, 1, 1).days(), 0);
+        assert_eq!(date(1969, 12, 31).days(), -1);
+        assert_eq!(date(2000, 2, 29).days(), 11_016);
+        assert_eq!(date(2024, 2, 29).days(), 19_782);
+        assert_eq!(date(1900, 3, 1).days(), -25_508);
+        assert_eq!(date(1600, 1, 1).days(), -135_140);
+        assert_eq!(date(1, 1, 1).days(), -719_162);
+    }
+
+    #[test]
+    fn days_count_through_every_date() {
+        // From before 1600 to past 2400, taking in leap years, centuries and 400-year eras
+        let mut expected = date(1599, 1, 1);
+        for days in date(1599, 1, 1).days()..date(2401, 12, 31).days() {
+            let date = Date::from_days(days);
+            assert_eq!(date, expected);
+            assert_eq!(date.days(), days);
+            assert_eq!(Date::parse(&date.to_string()), Ok(date));
+            expected = if date.day < days_in_month(date.year, date.month) {
+                Date {
+                    day: date.day + 1,
+                    ..date
+                }
+            } else if date.month < 12 {
+                Date {
+                    month: date.month + 1,
+                    day: 1,
+                    ..date
+                }
+            } else {
+                Date {
+                    year: date.year + 1,
+                    month: 1,
+                    day: 1,
+                }
+            };
+        }
+    }
+
+    #[test]
+    fn impossible_dates_are_rejected() {
+        for text in [
+            "2023-02-29",
+            "2100-02-29",
+            "2024-02-30",
+            "2024-13-01",
+            "2024-04-31",
+            "2024-4",
+        ] {
+            assert!(Date::parse(text).is_err(), "{}", text);
+        }
+        assert_eq!(Date::parse("2000-02-29"), Ok(date(2000, 2, 29)));
+    }
+
+    /// Every day's puzzle follows from its seed, so the seeds must never change
+    #[test]
+    fn seeds_are_pinned() {
+        assert_eq!(seed(date(2024, 2, 29), 6, Difficulty::Easy), 25_769_823_558);
+        assert_eq!(
+            seed(date(2024, 2, 29), 9, Difficulty::Hard),
+            281_513_631_436_102
+        );
+        assert_eq!(
+            seed(date(1969, 12, 31), 6, Difficulty::Easy),
+            6 << 32 | 0xffff_ffff
+        );
+    }
+}
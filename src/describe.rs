@@ -0,0 +1,106 @@
+//! The board described in words, a line of plain sentences per row, for reading with a screen
+//! reader rather than looking at.
+//!
+//! Each row is read left to right as runs of neighbouring cells that share an aquarium and a
+//! state, so a row reads "Row 1, hint 2: cells 1 to 4 dry in aquarium A, cells 5 to 6 flooded in
+//! aquarium B". Rows and columns are numbered from 1, as they would be said aloud.
+
+use std::fmt::Write;
+
+use crate::{Board, CellState, RenderMode, RenderOptions};
+
+/// The board as `Board::render_with` would draw it, in sentences instead
+pub fn describe(board: &Board, options: RenderOptions) -> String {
+    let show_cells = options.mode != RenderMode::Puzzle;
+    let show_remaining = matches!(options.mode, RenderMode::Working | RenderMode::Pencil);
+
+    let mut out = String::new();
+    writeln!(
+        out,
+        "{} by {} board with {} aquariums.",
+        board.width, board.height, board.n_partitions
+    )
+    .unwrap();
+    writeln!(
+        out,
+        "Column hints, left to right: {}.",
+        list(&board.col_hints)
+    )
+    .unwrap();
+
+    for iy in 0..board.height {
+        write!(out, "Row {}, hint {}", iy + 1, board.row_hints[iy]).unwrap();
+        if show_remaining {
+            write!(out, ", {} still to flood", board.row_remaining(iy)).unwrap();
+        }
+        let mut runs = Vec::new();
+        let mut start = 0;
+        for ix in 1..=board.width {
+            let same = ix < board.width
+                && board.partition_at(ix, iy) == board.partition_at(start, iy)
+                && (!show_cells || board.cell_state_at(ix, iy) == board.cell_state_at(start, iy));
+            if same {
+                continue;
+            }
+            let cells = if ix - start == 1 {
+                format!("cell {}", start + 1)
+            } else {
+                format!("cells {} to {}", start + 1, ix)
+            };
+            let state = if show_cells {
+                format!(" {}", state_word(board.cell_state_at(start, iy)))
+            } else {
+                String::new()
+            };
+            runs.push(format!(
+                "{}{} in aquarium {}",
+                cells,
+                state,
+                board.partition_label(board.partition_at(start, iy))
+            ));
+            start = ix;
+        }
+        writeln!(out, ": {}.", runs.join(", ")).unwrap();
+    }
+
+    if show_remaining {
+        let remaining: Vec<_> = (0..board.width).map(|ix| board.col_remaining(ix)).collect();
+        writeln!(
+            out,
+            "Columns still to flood, left to right: {}.",
+            list(&remaining)
+        )
+        .unwrap();
+    }
+    if options.mode == RenderMode::Pencil {
+        for partition in board.partitions() {
+            let label = board.partition_label(partition);
+            match board.depth_range(partition) {
+                Some((least, most)) if least == most => {
+                    writeln!(out, "Aquarium {} is {} rows deep.", label, least)
+                }
+                Some((least, most)) => writeln!(
+                    out,
+                    "Aquarium {} can be filled {} to {} rows deep.",
+                    label, least, most
+                ),
+                None => writeln!(out, "No depth fits aquarium {}.", label),
+            }
+            .unwrap();
+        }
+    }
+    out
+}
+
+fn state_word(state: CellState) -> &'static str {
+    match state {
+        CellState::Empty => "undecided",
+        CellState::Flooded => "flooded",
+        CellState::Invalid => "dry",
+    }
+}
+
+fn list(numbers: &[isize]) -> String {
+    let words: Vec<_> = numbers.iter().map(|number| number.to_string()).collect();
+    words.join(", ")
+}
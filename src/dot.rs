@@ -0,0 +1,73 @@
+//! Graphviz export of a puzzle's aquariums, for studying the shape of generated layouts.
+//!
+//! Each aquarium is a node labelled with its size and the rows it spans, which are also given as
+//! the `cells`, `top` and `bottom` attributes for tools reading the graph. Aquariums that touch are
+//! joined by an edge whose `border` attribute counts the cell sides they share.
+
+use std::fmt::Write;
+
+use crate::Board;
+
+/// The aquariums of `board` and how they touch, as an undirected DOT graph called `name`
+pub fn graph(name: &str, board: &Board) -> String {
+    let n = board.n_partitions;
+    let mut size = vec![0; n];
+    let mut top = vec![usize::MAX; n];
+    let mut bottom = vec![0; n];
+    // Shared cell sides between each pair of aquariums, lower number first
+    let mut borders = vec![vec![0; n]; n];
+    for iy in 0..board.height {
+        for ix in 0..board.width {
+            let partition = board.partition_at(ix, iy);
+            size[partition] += 1;
+            top[partition] = top[partition].min(iy);
+            bottom[partition] = bottom[partition].max(iy);
+            let mut neighbours = Vec::with_capacity(2);
+            if ix + 1 < board.width {
+                neighbours.push(board.partition_at(ix + 1, iy));
+            }
+            if iy + 1 < board.height {
+                neighbours.push(board.partition_at(ix, iy + 1));
+            }
+            for other in neighbours {
+                if other != partition {
+                    borders[partition.min(other)][partition.max(other)] += 1;
+                }
+            }
+        }
+    }
+
+    let mut out = String::new();
+    writeln!(out, "graph \"{}\" {{", escape(name)).unwrap();
+    for partition in board.partitions() {
+        let rows = if top[partition] == bottom[partition] {
+            format!("row {}", top[partition])
+        } else {
+            format!("rows {}-{}", top[partition], bottom[partition])
+        };
+        writeln!(
+            out,
+            "  p{} [label=\"{}\\n{} cells\\n{}\", cells={}, top={}, bottom={}];",
+            partition,
+            escape(&board.partition_label(partition)),
+            size[partition],
+            rows,
+            size[partition],
+            top[partition],
+            bottom[partition]
+        )
+        .unwrap();
+    }
+    for (a, row) in borders.iter().enumerate() {
+        for (b, &border) in row.iter().enumerate().filter(|(_, &border)| border > 0) {
+            writeln!(out, "  p{} -- p{} [border={}];", a, b, border).unwrap();
+        }
+    }
+    writeln!(out, "}}").unwrap();
+    out
+}
+
+/// The text with the characters special inside a quoted DOT string escaped
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
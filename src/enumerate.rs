@@ -0,0 +1,237 @@
+//! Every puzzle on a tiny board, for studying how aquarium puzzles behave as a whole.
+//!
+//! A layout is found for every set of walls between neighbouring cells in which each wall divides
+//! two different aquariums, so each way of splitting the board into connected aquariums turns up
+//! exactly once. Every way of filling a layout's aquariums gives a set of hints, and the hint sets
+//! only one filling gives are the puzzles with a unique solution. The rules can be run on each of
+//! those to see how many they solve without the search, though that takes far longer than the
+//! counting.
+
+use std::collections::HashMap;
+use std::io::{self, Write};
+
+use rayon::prelude::*;
+
+use crate::{regions, Board};
+
+/// The most walls a board can have between its cells, since every set of them is tried
+pub const MAX_WALLS: usize = 32;
+
+/// Largest width or height, so each hint fits in four bits of a packed hint set
+const MAX_SIDE: usize = 8;
+
+/// What was found for the layouts with a given number of aquariums
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Tally {
+    pub layouts: u64,
+    /// Ways of filling the aquariums, over all of the layouts
+    pub fillings: u64,
+    /// Different hint sets the fillings give
+    pub hint_sets: u64,
+    /// Hint sets given by one filling alone
+    pub unique: u64,
+    /// Unique hint sets the rules solve without the search, when they were run
+    pub by_rules: u64,
+}
+
+impl Tally {
+    fn add(&mut self, other: &Tally) {
+        self.layouts += other.layouts;
+        self.fillings += other.fillings;
+        self.hint_sets += other.hint_sets;
+        self.unique += other.unique;
+        self.by_rules += other.by_rules;
+    }
+}
+
+/// Every layout of a `width` by `height` board and every puzzle on it, tallied by the number of
+/// aquariums: entry `n` is for the layouts with `n` aquariums. With `rules` set, the rules are run
+/// on every puzzle with a unique solution.
+pub fn enumerate(width: usize, height: usize, rules: bool) -> Result<Vec<Tally>, String> {
+    if width == 0 || height == 0 || width > MAX_SIDE || height > MAX_SIDE {
+        return Err(format!(
+            "Boards from 1 to {} cells a side can be enumerated, not {}x{}",
+            MAX_SIDE, width, height
+        ));
+    }
+    let n_walls = height * (width - 1) + (height - 1) * width;
+    if n_walls > MAX_WALLS {
+        return Err(format!(
+            "A {}x{} board has {} walls, and only boards with up to {} can be enumerated",
+            width, height, n_walls, MAX_WALLS
+        ));
+    }
+
+    let n_cells = width * height;
+    let tallies = (0..1u64 << n_walls)
+        .into_par_iter()
+        .filter_map(|mask| {
+            let walls: Vec<_> = (0..n_walls).map(|i| mask & 1 << i != 0).collect();
+            layout(width, height, &walls)
+        })
+        .fold(
+            || vec![Tally::default(); n_cells + 1],
+            |mut tallies, board| {
+                tallies[board.n_partitions].add(&tally(&board, rules));
+                tallies
+            },
+        )
+        .reduce(
+            || vec![Tally::default(); n_cells + 1],
+            |mut tallies, other| {
+                for (tally, other) in tallies.iter_mut().zip(&other) {
+                    tally.add(other);
+                }
+                tallies
+            },
+        );
+    Ok(tallies)
+}
+
+/// The board the walls split into aquariums, or None if a wall stands inside an aquarium, as the
+/// same layout turns up without it
+fn layout(width: usize, height: usize, walls: &[bool]) -> Option<Board> {
+    let (regions, _) = regions(width, height, walls);
+    let floors = &walls[height * (width - 1)..];
+    for iy in 0..height {
+        for ix in 0..width - 1 {
+            let i = iy * width + ix;
+            if walls[iy * (width - 1) + ix] && regions[i] == regions[i + 1] {
+                return None;
+            }
+        }
+    }
+    for iy in 0..height - 1 {
+        for ix in 0..width {
+            let i = iy * width + ix;
+            if floors[i] && regions[i] == regions[i + width] {
+                return None;
+            }
+        }
+    }
+
+    let mut board = Board::make(width, height);
+    for (cell, region) in board.cells.iter_mut().zip(regions) {
+        cell.partition = region;
+    }
+    board.index_partitions();
+    Some(board)
+}
+
+/// Fill the layout every way it can be filled and count the hint sets that come of it
+fn tally(board: &Board, rules: bool) -> Tally {
+    // The hints are packed four bits a line, rows then columns, so the hint set of a filling is
+    // the sum of what each aquarium's level adds
+    let line_bit = |line: usize| 1u64 << (4 * line);
+    let levels: Vec<Vec<u64>> = board
+        .partitions()
+        .into_iter()
+        .map(|partition| {
+            let mut rows: Vec<_> = (0..board.height)
+                .filter(|&iy| (0..board.width).any(|ix| board.partition_at(ix, iy) == partition))
+                .collect();
+            rows.insert(0, board.height);
+            rows.into_iter()
+                .map(|level| {
+                    let mut packed = 0;
+                    for iy in level..board.height {
+                        for ix in 0..board.width {
+                            if board.partition_at(ix, iy) == partition {
+                                packed += line_bit(iy) + line_bit(board.height + ix);
+                            }
+                        }
+                    }
+                    packed
+                })
+                .collect()
+        })
+        .collect();
+
+    let mut hint_sets = HashMap::new();
+    fill(&levels, 0, &mut hint_sets);
+
+    let mut tally = Tally {
+        layouts: 1,
+        fillings: hint_sets.values().sum(),
+        hint_sets: hint_sets.len() as u64,
+        ..Tally::default()
+    };
+    tally.unique = hint_sets.values().filter(|&&count| count == 1).count() as u64;
+    if !rules {
+        return tally;
+    }
+    for (&packed, _) in hint_sets.iter().filter(|(_, &count)| count == 1) {
+        let mut puzzle = board.clone();
+        for iy in 0..board.height {
+            puzzle.row_hints[iy] = (packed >> (4 * iy) & 15) as isize;
+        }
+        for ix in 0..board.width {
+            puzzle.col_hints[ix] = (packed >> (4 * (board.height + ix)) & 15) as isize;
+        }
+        puzzle.solve();
+        if puzzle.is_solved() {
+            tally.by_rules += 1;
+        }
+    }
+    tally
+}
+
+/// Count the fillings giving each hint set, choosing a level for each aquarium in turn
+fn fill(levels: &[Vec<u64>], packed: u64, hint_sets: &mut HashMap<u64, u64>) {
+    match levels.split_first() {
+        Some((options, rest)) => {
+            for &added in options {
+                fill(rest, packed + added, hint_sets);
+            }
+        }
+        None => *hint_sets.entry(packed).or_insert(0) += 1,
+    }
+}
+
+/// Write the tallies for a `width` by `height` board as a table, a row per number of aquariums,
+/// with what the rules solved if they were run
+pub fn write_report<W: Write>(
+    mut out: W,
+    width: usize,
+    height: usize,
+    tallies: &[Tally],
+    rules: bool,
+) -> io::Result<()> {
+    let mut total = Tally::default();
+    for tally in tallies {
+        total.add(tally);
+    }
+    writeln!(out, "{}x{}: {} layouts", width, height, total.layouts)?;
+    write!(
+        out,
+        "{:>9} {:>9} {:>11} {:>11} {:>9}",
+        "aquariums", "layouts", "fillings", "hint sets", "unique"
+    )?;
+    if rules {
+        write!(out, " {:>9} {:>8}", "by rules", "% rules")?;
+    }
+    writeln!(out)?;
+    let rows = tallies
+        .iter()
+        .enumerate()
+        .filter(|(_, tally)| tally.layouts > 0)
+        .map(|(n, tally)| (n.to_string(), tally))
+        .chain(std::iter::once(("all".to_string(), &total)));
+    for (key, tally) in rows {
+        write!(
+            out,
+            "{:>9} {:>9} {:>11} {:>11} {:>9}",
+            key, tally.layouts, tally.fillings, tally.hint_sets, tally.unique
+        )?;
+        if rules {
+            let share = if tally.unique == 0 {
+                0.0
+            } else {
+                100.0 * tally.by_rules as f64 / tally.unique as f64
+            };
+            write!(out, " {:>9} {:>7.1}%", tally.by_rules, share)?;
+        }
+        writeln!(out)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,209 @@
+//! Subset sums of the undecided aquariums in a row, for the subset-sum rule, and of the levels of
+//! the aquariums in a band of rows, for the band rule.
+//!
+//! Every cell of an aquarium in a row shares a state, so the cells a row still needs have to be made
+//! up of whole aquariums. Each row keeps a count of the ways its undecided aquariums add up to every
+//! sum, and when an aquarium is decided it is taken back out of the counts rather than the counts
+//! being worked out again.
+
+use alloc::vec;
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+
+/// Up to this many segments, no count of combinations can wrap around
+const EXACT_SEGMENTS: usize = 128;
+
+/// The subset sums for one row
+#[derive(Default)]
+pub struct RowFeasibility {
+    /// The undecided aquariums in the row as of the last update: (partition, cells in the row)
+    segments: Vec<(usize, isize)>,
+    /// `ways[t]`: how many combinations of the segments add up to t cells, wrapping past `u128`
+    ways: Vec<u128>,
+    /// Whether the counts have been built at all
+    built: bool,
+}
+
+impl RowFeasibility {
+    /// Bring the counts up to date with the row's undecided aquariums, taking out the ones decided
+    /// since the last update and adding any that are undecided again.
+    /// Returns whether anything changed.
+    pub fn update(&mut self, segments: &[(usize, isize)]) -> bool {
+        if !self.built {
+            self.ways = vec![1];
+            self.built = true;
+        } else if self.segments == segments {
+            return false;
+        }
+        let size = |&(_, size): &(usize, isize)| usize::try_from(size).unwrap();
+
+        let (before, ways) = (&self.segments, &mut self.ways);
+        for segment in before.iter().filter(|segment| !segments.contains(segment)) {
+            remove_segment(ways, size(segment));
+        }
+        for segment in segments.iter().filter(|segment| !before.contains(segment)) {
+            add_segment(ways, size(segment));
+        }
+        self.segments = segments.to_vec();
+        true
+    }
+
+    /// Whether `target` cells can be made from the segments other than one of `size` cells
+    fn reachable_without(&self, size: isize, target: isize) -> bool {
+        let (size, target) = match (usize::try_from(size), usize::try_from(target)) {
+            (Ok(size), Ok(target)) => (size, target),
+            _ => return false,
+        };
+        // Taking the segment back out of the counts, for the one sum wanted
+        let ways = (0..=target / size)
+            .map(|j| self.ways.get(target - j * size).copied().unwrap_or(0))
+            .enumerate()
+            .fold(0u128, |ways, (j, term)| {
+                if j % 2 == 0 {
+                    ways.wrapping_add(term)
+                } else {
+                    ways.wrapping_sub(term)
+                }
+            });
+        // A count that wrapped round to 0 can't be told from no ways at all
+        ways != 0 || self.segments.len() > EXACT_SEGMENTS
+    }
+
+    /// For each undecided aquarium in the row: whether the row can still reach `remainder` flooded cells
+    /// with the aquarium flooded, and whether it can with the aquarium left dry
+    pub fn options(&self, remainder: isize) -> Vec<(usize, bool, bool)> {
+        self.segments
+            .iter()
+            .map(|&(partition, size)| {
+                let flooded = self.reachable_without(size, remainder - size);
+                let dry = self.reachable_without(size, remainder);
+                (partition, flooded, dry)
+            })
+            .collect()
+    }
+}
+
+/// Count the combinations once a segment of `size` cells may also be added
+fn add_segment(ways: &mut Vec<u128>, size: usize) {
+    ways.resize(ways.len() + size, 0);
+    for t in (size..ways.len()).rev() {
+        ways[t] = ways[t].wrapping_add(ways[t - size]);
+    }
+}
+
+/// Undo `add_segment` for a segment of `size` cells
+fn remove_segment(ways: &mut Vec<u128>, size: usize) {
+    for t in size..ways.len() {
+        ways[t] = ways[t].wrapping_sub(ways[t - size]);
+    }
+    ways.truncate(ways.len() - size);
+}
+
+/// For each group of choices, which of its choices can be added to one choice from every other
+/// group to make `target`
+pub fn feasible_choices(groups: &[Vec<usize>], target: usize) -> Vec<Vec<bool>> {
+    // Sums past the target can't come back down to it, so they aren't kept
+    let add_group = |sums: &[bool], group: &[usize]| {
+        let mut next = vec![false; sums.len()];
+        for &choice in group {
+            for t in choice..sums.len() {
+                next[t] = next[t] || sums[t - choice];
+            }
+        }
+        next
+    };
+    let mut sums = vec![false; target + 1];
+    sums[0] = true;
+    let mut prefix = vec![sums.clone()];
+    for group in groups {
+        sums = add_group(&sums, group);
+        prefix.push(sums.clone());
+    }
+    let mut sums = vec![false; target + 1];
+    sums[0] = true;
+    let mut suffix = vec![sums.clone()];
+    for group in groups.iter().rev() {
+        sums = add_group(&sums, group);
+        suffix.push(sums.clone());
+    }
+    suffix.reverse();
+
+    groups
+        .iter()
+        .enumerate()
+        .map(|(i, group)| {
+            group
+                .iter()
+                .map(|&choice| {
+                    choice <= target
+                        && (0..=target - choice)
+                            .any(|a| prefix[i][a] && suffix[i + 1][target - choice - a])
+                })
+                .collect()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Whether some of `sizes` add up to `target`, trying every combination
+    fn brute_reachable(sizes: &[isize], target: isize) -> bool {
+        (0..1u32 << sizes.len()).any(|mask| {
+            let sum: isize = (0..sizes.len())
+                .filter(|i| mask & 1 << i != 0)
+                .map(|i| sizes[i])
+                .sum();
+            sum == target
+        })
+    }
+
+    #[test]
+    fn updating_matches_building_from_scratch() {
+        let mut seed = 0x2545_f491_4f6c_dd1du64;
+        let mut random = |n: u64| {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            seed % n
+        };
+        let all: Vec<(usize, isize)> = (0..10)
+            .map(|partition| (partition, 1 + random(5) as isize))
+            .collect();
+
+        let mut updated = RowFeasibility::default();
+        for _ in 0..200 {
+            // Mostly decide aquariums, sometimes undo back to more of them, as the search does
+            let keep = if random(4) == 0 { 2 } else { 6 };
+            let segments: Vec<_> = all.iter().copied().filter(|_| random(8) < keep).collect();
+            updated.update(&segments);
+            let mut scratch = RowFeasibility::default();
+            scratch.update(&segments);
+
+            let total: isize = segments.iter().map(|&(_, size)| size).sum();
+            for remainder in -1..=total + 1 {
+                let options = updated.options(remainder);
+                assert_eq!(options, scratch.options(remainder));
+                for (i, &(partition, flooded, dry)) in options.iter().enumerate() {
+                    let size = segments[i].1;
+                    let others: Vec<_> = segments
+                        .iter()
+                        .filter(|&&(other, _)| other != partition)
+                        .map(|&(_, size)| size)
+                        .collect();
+                    assert_eq!(flooded, brute_reachable(&others, remainder - size));
+                    assert_eq!(dry, brute_reachable(&others, remainder));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn an_unchanged_row_is_not_updated() {
+        let mut feasibility = RowFeasibility::default();
+        assert!(feasibility.update(&[(0, 2), (3, 1)]));
+        assert!(!feasibility.update(&[(0, 2), (3, 1)]));
+        assert!(feasibility.update(&[(3, 1)]));
+    }
+}
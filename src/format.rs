@@ -0,0 +1,1032 @@
+//! Plain text puzzle files.
+//!
+//! ```text
+//! name: b0
+//! difficulty: easy
+//! source: puzzle-aquarium.com
+//! id: 3,095,209
+//! cols: 1 2 1 3 5 4
+//! rows: 2 4 3 2 1 4
+//! 0 0 0 0 1 1
+//! 0 0 2 2 1 1
+//! 3 0 3 2 4 5
+//! 3 3 3 2 4 5
+//! 3 3 3 3 3 5
+//! 3 3 5 5 5 5
+//! ```
+//!
+//! Lines starting with `#` are comments. `key: value` lines give the hints and the optional name,
+//! difficulty, `source`, `id`, `author` and `date`; every other line is a row of the grid, giving
+//! the aquarium of each cell.
+//!
+//! An aquarium's label can be any word without spaces or `:` that doesn't start with `#`, e.g. `7`,
+//! `-1` or `A`, so its row can't be taken for a key or a comment. The aquariums are numbered in the
+//! order their labels first appear, and the labels are kept for display.
+//!
+//! A `hints: at-least` line makes the hints lower bounds: each row and column holds at least that
+//! many flooded cells rather than exactly that many. `hints: exact` is the default.
+//!
+//! A puzzle that is partly done can give the state of its cells with a `cells:` line per row, e.g.
+//! `cells: . . . * * .`, using `*` for water, `X` for a dry cell and `.` for one not yet decided.
+//! The cells below a flooded one in its aquarium are flooded too, and those above a dry one dried,
+//! as the water lies level. The solver starts from those cells and finishes the rest, or reports
+//! that it can't.
+//!
+//! A solution can be given more briefly with a `levels:` line: how many rows of each aquarium are
+//! under water, counting up from its bottom row, with one number per aquarium in the order their
+//! labels first appear, e.g. `levels: 2 2 1 0 1 3`. A puzzle whose cells are all decided is
+//! written with its levels.
+//!
+//! A file can hold several puzzles one after another. A puzzle ends at the first blank line after its
+//! grid, or where a `key: value` line other than `cells:` or `levels:` follows its grid.
+//!
+//! A `.csv` file holds a single puzzle's hints and layout, one cell per field; see `parse_csv`.
+//! A `.png` file holds a single puzzle painted as an image; see the `image` module.
+//! A `.aqb` file holds any number of puzzles in a compact binary form; see the `binary` module.
+//! A puzzle from the janko.at archive can be read as is; see `parse_janko`.
+//! The `formats` module tells them apart.
+//!
+//! A bad puzzle is reported with the line and column of the mistake, quoting the line.
+
+use std::borrow::Cow;
+use std::collections::{HashMap, VecDeque};
+use std::convert::TryFrom;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+use crate::formats::{self, PuzzleFormat};
+use crate::stats::{BatchEntry, Metadata};
+use crate::{Board, CellState, HintMode};
+
+/// Whether a file found while walking a directory holds a puzzle
+pub fn is_puzzle_file(path: &Path) -> bool {
+    formats::for_extension(path).is_some()
+}
+
+/// Read every puzzle in a file, in the format its contents or extension show. Puzzles that don't
+/// name themselves are named after the file, numbered from 1 if the file holds more than one.
+pub fn read_file(path: &Path) -> Result<Vec<BatchEntry>, String> {
+    read_file_as(path, None)
+}
+
+/// Read every puzzle in a file in the given format, or in the format it looks to be in
+pub fn read_file_as(
+    path: &Path,
+    format: Option<&dyn PuzzleFormat>,
+) -> Result<Vec<BatchEntry>, String> {
+    let bytes = std::fs::read(path).map_err(|err| err.to_string())?;
+    let format = format.unwrap_or_else(|| formats::detect(path, &bytes));
+    let mut entries = format.parse(path, &bytes)?;
+    let stem = file_stem(path);
+    let several = entries.len() > 1;
+    for (i, entry) in entries.iter_mut().enumerate() {
+        name_after_file(entry, &stem, i + 1, several);
+    }
+    Ok(entries)
+}
+
+fn file_stem(path: &Path) -> String {
+    path.file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+/// Name a puzzle that doesn't name itself after its file, and its number in the file if there
+/// are several
+fn name_after_file(entry: &mut BatchEntry, stem: &str, number: usize, several: bool) {
+    if entry.name.is_empty() {
+        entry.name = if several {
+            format!("{} #{}", stem, number)
+        } else {
+            stem.to_string()
+        };
+    }
+}
+
+/// Start reading the puzzles in a file one at a time, in the format its start or extension
+/// shows. Only as much of the file is held in memory as the format needs to read one puzzle, so a
+/// corpus of any size can be read.
+pub fn stream_file(path: &Path) -> Result<FilePuzzles, String> {
+    let mut reader = BufReader::new(File::open(path).map_err(|err| err.to_string())?);
+    let start = reader.fill_buf().map_err(|err| err.to_string())?;
+    let format = formats::detect(path, start);
+    let mut puzzles = format.stream(path, reader)?;
+    // Two puzzles are read ahead, to tell whether there are several to number
+    let ahead: VecDeque<_> = puzzles.by_ref().take(2).collect();
+    Ok(FilePuzzles {
+        several: ahead.len() > 1,
+        puzzles,
+        stem: file_stem(path),
+        ahead,
+        n_read: 0,
+    })
+}
+
+/// The puzzles of a file, read as they're needed and named as `read_file` names them
+pub struct FilePuzzles {
+    puzzles: formats::Puzzles,
+    stem: String,
+    /// Puzzles read but not yet handed out
+    ahead: VecDeque<Result<BatchEntry, String>>,
+    n_read: usize,
+    several: bool,
+}
+
+/// A puzzle read from a file, or why it couldn't be
+pub struct Streamed {
+    /// Its place in the file, counting from 1
+    pub number: usize,
+    /// Whether the file holds other puzzles too
+    pub several: bool,
+    pub entry: Result<BatchEntry, String>,
+}
+
+impl Streamed {
+    /// The file's path, followed by `#n` for the nth puzzle of a file holding several
+    pub fn id(&self, path: &Path) -> String {
+        if self.several {
+            format!("{}#{}", path.display(), self.number)
+        } else {
+            path.display().to_string()
+        }
+    }
+}
+
+impl Iterator for FilePuzzles {
+    type Item = Streamed;
+
+    fn next(&mut self) -> Option<Streamed> {
+        let mut entry = match self.ahead.pop_front() {
+            Some(entry) => entry,
+            None => self.puzzles.next()?,
+        };
+        self.n_read += 1;
+        if let Ok(entry) = &mut entry {
+            name_after_file(entry, &self.stem, self.n_read, self.several);
+        }
+        Some(Streamed {
+            number: self.n_read,
+            several: self.several,
+            entry,
+        })
+    }
+}
+
+/// Read the puzzle at `index`, counting from 1, from a file. Without an index the file must hold
+/// a single puzzle.
+pub fn read_puzzle(path: &Path, index: Option<usize>) -> Result<BatchEntry, String> {
+    let mut entries = read_file(path)?;
+    match index {
+        Some(index) if index == 0 || index > entries.len() => Err(format!(
+            "There is no puzzle {}; the file holds {}",
+            index,
+            entries.len()
+        )),
+        Some(index) => Ok(entries.swap_remove(index - 1)),
+        None if entries.len() == 1 => Ok(entries.pop().unwrap()),
+        None => Err(format!(
+            "The file holds {} puzzles; pick one with --index",
+            entries.len()
+        )),
+    }
+}
+
+/// Parse a single puzzle
+pub fn parse(text: &str) -> Result<BatchEntry, String> {
+    let lines: Vec<_> = text.lines().enumerate().collect();
+    parse_lines(&lines)
+}
+
+/// Parse every puzzle in the text
+pub fn parse_all(text: &str) -> Result<Vec<BatchEntry>, String> {
+    let mut puzzles = Vec::new();
+    let mut splitter = PuzzleLines::default();
+    for (i, line) in text.lines().enumerate() {
+        if let Some(puzzle) = splitter.push(i, line.to_string()) {
+            puzzles.push(puzzle?);
+        }
+    }
+    if let Some(puzzle) = splitter.finish() {
+        puzzles.push(puzzle?);
+    }
+    Ok(puzzles)
+}
+
+/// Splits the lines of a file into puzzles as they come, parsing each puzzle once its last line
+/// has been seen
+#[derive(Default)]
+struct PuzzleLines {
+    /// The lines of the puzzle so far, numbered from 0 in the file
+    lines: Vec<(usize, String)>,
+    /// Whether the puzzle's grid has started
+    in_grid: bool,
+    /// The puzzles parsed so far
+    n_parsed: usize,
+}
+
+impl PuzzleLines {
+    /// Take the next line of the file, numbered from 0. Returns the puzzle it ends, if any.
+    fn push(&mut self, i: usize, line: String) -> Option<Result<BatchEntry, String>> {
+        let trimmed = line.trim();
+        if trimmed.starts_with('#') {
+            return None;
+        }
+        let key = trimmed.split_once(':').map(|(key, _)| key.trim());
+        let ends_puzzle =
+            trimmed.is_empty() || key.is_some_and(|key| key != "cells" && key != "levels");
+        let puzzle = if self.in_grid && ends_puzzle {
+            Some(self.parse())
+        } else {
+            None
+        };
+        self.in_grid |= !ends_puzzle;
+        self.lines.push((i, line));
+        puzzle
+    }
+
+    /// The puzzle the file ends with, if its last lines hold one
+    fn finish(&mut self) -> Option<Result<BatchEntry, String>> {
+        if self.in_grid || self.lines.iter().any(|(_, line)| !line.trim().is_empty()) {
+            Some(self.parse())
+        } else if self.n_parsed == 0 {
+            Some(Err("No puzzles".to_string()))
+        } else {
+            None
+        }
+    }
+
+    fn parse(&mut self) -> Result<BatchEntry, String> {
+        let lines: Vec<_> = self
+            .lines
+            .iter()
+            .map(|(i, line)| (*i, line.as_str()))
+            .collect();
+        let puzzle = parse_numbered(&lines, self.n_parsed);
+        self.lines.clear();
+        self.in_grid = false;
+        self.n_parsed += 1;
+        puzzle
+    }
+}
+
+/// Reads the puzzles of a text file one at a time, as its lines come in
+pub struct TextPuzzles<R> {
+    lines: std::io::Lines<R>,
+    splitter: PuzzleLines,
+    /// The number of the next line, from 0
+    line: usize,
+    done: bool,
+}
+
+impl<R: BufRead> TextPuzzles<R> {
+    pub fn new(reader: R) -> TextPuzzles<R> {
+        TextPuzzles {
+            lines: reader.lines(),
+            splitter: PuzzleLines::default(),
+            line: 0,
+            done: false,
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for TextPuzzles<R> {
+    type Item = Result<BatchEntry, String>;
+
+    /// The next puzzle, or why it couldn't be parsed. The file ends at a line that can't be read.
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.done {
+            let i = self.line;
+            self.line += 1;
+            match self.lines.next() {
+                Some(Ok(line)) => {
+                    if let Some(puzzle) = self.splitter.push(i, line) {
+                        return Some(puzzle);
+                    }
+                }
+                Some(Err(err)) => {
+                    self.done = true;
+                    return Some(Err(format!("Line {}: {}", i + 1, err)));
+                }
+                None => {
+                    self.done = true;
+                    return self.splitter.finish();
+                }
+            }
+        }
+        None
+    }
+}
+
+/// Parse the puzzle after the first `n_before`, saying which puzzle it is if it's bad
+fn parse_numbered(lines: &[(usize, &str)], n_before: usize) -> Result<BatchEntry, String> {
+    parse_lines(lines).map_err(|err| format!("Puzzle {}: {}", n_before + 1, err))
+}
+
+/// Parse one puzzle from its lines, numbered from 0 in the file they came from
+fn parse_lines(lines: &[(usize, &str)]) -> Result<BatchEntry, String> {
+    let mut parts = Parts::default();
+    for &(i, line) in lines {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        let Some(colon) = line.find(':') else {
+            parts.grid.push(Located::new(i, line, 0));
+            continue;
+        };
+        let key = line[..colon].trim();
+        let value = line[colon + 1..].trim().to_string();
+        match key {
+            "name" => parts.name = value,
+            "difficulty" => parts.difficulty = value,
+            "source" => parts.metadata.source = value,
+            "id" => parts.metadata.id = value,
+            "author" => parts.metadata.author = value,
+            "date" => parts.metadata.date = value,
+            "hints" => {
+                parts.hint_mode = HintMode::from_name(&value).ok_or_else(|| {
+                    let offset =
+                        colon + 1 + line[colon + 1..].len() - line[colon + 1..].trim_start().len();
+                    error_at(i, line, offset, "expected exact or at-least")
+                })?
+            }
+            "cols" => parts.col_hints = Some(Located::new(i, line, colon + 1).numbers()?),
+            "rows" => parts.row_hints = Some(Located::new(i, line, colon + 1).numbers()?),
+            "cells" => parts.cells.push(Located::new(i, line, colon + 1)),
+            "levels" => parts.levels = Some(Located::new(i, line, colon + 1).numbers()?),
+            _ => {
+                let offset = line.len() - line.trim_start().len();
+                return Err(error_at(i, line, offset, &format!("unknown key '{}'", key)));
+            }
+        }
+    }
+    parts.build()
+}
+
+/// Parse a puzzle from CSV, one cell per field: the column hints along the first row, after a
+/// field for the corner, then a row per grid row giving its hint and the aquarium of each cell.
+///
+/// ```text
+/// ,1,2,1,3,5,4
+/// 2,0,0,0,0,1,1
+/// 4,0,0,2,2,1,1
+/// ```
+///
+/// Blank rows and empty fields at the end of a row are skipped, as spreadsheets tend to leave them.
+pub fn parse_csv(text: &str) -> Result<BatchEntry, String> {
+    let mut rows = text.lines().enumerate().filter_map(|(i, line)| {
+        let mut fields = csv_fields(i, line);
+        while fields.last().is_some_and(|field| field.text.is_empty()) {
+            fields.pop();
+        }
+        (!fields.is_empty()).then_some((i, line, fields))
+    });
+
+    let mut parts = Parts::default();
+    let (i, line, mut header) = rows.next().ok_or("Missing column hints")?;
+    header.remove(0);
+    parts.col_hints = Some(
+        Located {
+            i,
+            line,
+            words: header,
+        }
+        .numbers()?,
+    );
+
+    let mut row_hints = Vec::new();
+    let mut end = (i, line);
+    for (i, line, mut fields) in rows {
+        row_hints.push(fields.remove(0));
+        parts.grid.push(Located {
+            i,
+            line,
+            words: fields,
+        });
+        end = (i, line);
+    }
+    let (i, line) = end;
+    parts.row_hints = Some(
+        Located {
+            i,
+            line,
+            words: row_hints,
+        }
+        .numbers()?,
+    );
+    parts.build()
+}
+
+/// Parse a puzzle in the text format of the janko.at archive: `[section]` headers, `key = value`
+/// lines under `[setup]`, the clues in `[problem]` and the aquarium of each cell in `[areas]`.
+///
+/// ```text
+/// [setup]
+/// author = Otto Janko
+/// [problem]
+/// - 1 2 1 3 5 4
+/// 2 - - - - - -
+/// 4 - - - - - -
+/// [areas]
+/// a a a a b b
+/// a a c c b b
+/// [solution]
+/// ...
+/// [end]
+/// ```
+///
+/// The clues run along the top and left of the problem grid, with a `-` in the corner, or along
+/// the bottom and right. The rest of the problem grid, the solution and any other section are
+/// skipped, as is anything after `[end]`.
+pub fn parse_janko(text: &str) -> Result<BatchEntry, String> {
+    let mut parts = Parts::default();
+    let mut section = String::new();
+    let mut problem = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            continue;
+        }
+        if let Some(name) = trimmed
+            .strip_prefix('[')
+            .and_then(|it| it.strip_suffix(']'))
+        {
+            section = name.to_ascii_lowercase();
+            if section == "end" {
+                break;
+            }
+            continue;
+        }
+        match section.as_str() {
+            "setup" => {
+                let Some((key, value)) = trimmed.split_once('=') else {
+                    continue;
+                };
+                let value = value.trim().to_string();
+                match key.trim() {
+                    "title" => parts.name = value,
+                    "author" => parts.metadata.author = value,
+                    "source" => parts.metadata.source = value,
+                    _ => {}
+                }
+            }
+            "problem" => problem.push(Located::new(i, line, 0)),
+            "areas" => parts.grid.push(Located::new(i, line, 0)),
+            _ => {}
+        }
+    }
+    if parts.metadata.source.is_empty() {
+        parts.metadata.source = "janko.at".to_string();
+    }
+
+    // The row of column clues is the end row with the more numbers in it
+    let numbers = |row: Option<&Located>| {
+        row.map_or(0, |row| {
+            row.words
+                .iter()
+                .filter(|word| word.text.parse::<usize>().is_ok())
+                .count()
+        })
+    };
+    let top_left = numbers(problem.first()) >= numbers(problem.last());
+    let corner = if top_left {
+        problem.first().and_then(|row| row.words.first())
+    } else {
+        problem.last().and_then(|row| row.words.last())
+    };
+    match corner {
+        None => return Err("Missing [problem]".to_string()),
+        Some(word) if word.text != "-" => {
+            return Err(word.error("expected a '-' in the corner between the clues"))
+        }
+        Some(_) => {}
+    }
+    let mut clues = if top_left {
+        problem.remove(0)
+    } else {
+        problem.pop().unwrap()
+    };
+    let mut row_hints = Vec::new();
+    for mut row in problem {
+        row_hints.push(if top_left {
+            row.words.remove(0)
+        } else {
+            row.words.pop().unwrap()
+        });
+    }
+    if top_left {
+        clues.words.remove(0);
+    } else {
+        clues.words.pop();
+    }
+    let (i, line) = (clues.i, clues.line);
+    parts.col_hints = Some(clues.numbers()?);
+    parts.row_hints = Some(
+        Located {
+            i,
+            line,
+            words: row_hints,
+        }
+        .numbers()?,
+    );
+    parts.build()
+}
+
+/// The puzzle in the text format, as read by `parse`. The cells' states are left out, unless
+/// they are all decided and can be written as the aquariums' levels.
+pub fn to_text(entry: &BatchEntry) -> String {
+    let board = &entry.board;
+    let mut out = String::new();
+    let keys = [
+        ("name", &entry.name),
+        ("difficulty", &entry.difficulty),
+        ("source", &entry.metadata.source),
+        ("id", &entry.metadata.id),
+        ("author", &entry.metadata.author),
+        ("date", &entry.metadata.date),
+    ];
+    for (key, value) in keys {
+        if !value.is_empty() {
+            out += &format!("{}: {}\n", key, value);
+        }
+    }
+    let hints = |hints: &[isize]| {
+        let hints: Vec<_> = hints.iter().map(|hint| hint.to_string()).collect();
+        hints.join(" ")
+    };
+    if board.hint_mode != HintMode::Exact {
+        out += &format!("hints: {}\n", board.hint_mode.name());
+    }
+    out += &format!("cols: {}\n", hints(&board.col_hints));
+    out += &format!("rows: {}\n", hints(&board.row_hints));
+
+    let labels: Vec<_> = (0..board.n_partitions)
+        .map(|partition| board.partition_label(partition))
+        .collect();
+    let label_width = labels.iter().map(|label| label.len()).max().unwrap_or(1);
+    for iy in 0..board.height {
+        let row: Vec<_> = (0..board.width)
+            .map(|ix| format!("{:>1$}", labels[board.partition_at(ix, iy)], label_width))
+            .collect();
+        out += &row.join(" ");
+        out.push('\n');
+    }
+    if let Some(levels) = to_levels(board) {
+        out += &levels;
+    }
+    out
+}
+
+/// The `levels:` line giving the depth of each aquarium's water, if every cell is decided
+pub fn to_levels(board: &Board) -> Option<String> {
+    let depths: Vec<_> = board
+        .depths()?
+        .iter()
+        .map(|depth| depth.to_string())
+        .collect();
+    Some(format!("levels: {}\n", depths.join(" ")))
+}
+
+/// The puzzle's layout and hints as CSV, as read by `parse_csv`
+pub fn to_csv(board: &Board) -> String {
+    let field = |text: String| {
+        if text.contains([',', '"']) {
+            format!("\"{}\"", text.replace('"', "\"\""))
+        } else {
+            text
+        }
+    };
+    let mut out = String::new();
+    for hint in &board.col_hints {
+        out += &format!(",{}", hint);
+    }
+    out.push('\n');
+    for iy in 0..board.height {
+        out += &board.row_hints[iy].to_string();
+        for ix in 0..board.width {
+            out.push(',');
+            out += &field(board.partition_label(board.partition_at(ix, iy)));
+        }
+        out.push('\n');
+    }
+    out
+}
+
+/// The fields of a CSV line, trimmed and without their quotes
+fn csv_fields(i: usize, line: &str) -> Vec<Word<'_>> {
+    let mut fields = Vec::new();
+    let mut start = 0;
+    let mut quoted = false;
+    for (offset, c) in line
+        .char_indices()
+        .chain(std::iter::once((line.len(), ',')))
+    {
+        match c {
+            '"' => quoted = !quoted,
+            ',' if !quoted => {
+                let field = &line[start..offset];
+                let trimmed = field.trim();
+                let text = match trimmed
+                    .strip_prefix('"')
+                    .and_then(|it| it.strip_suffix('"'))
+                {
+                    // A quote inside a quoted field is written twice
+                    Some(quoted) if quoted.contains("\"\"") => {
+                        Cow::Owned(quoted.replace("\"\"", "\""))
+                    }
+                    Some(quoted) => Cow::Borrowed(quoted),
+                    None => Cow::Borrowed(trimmed),
+                };
+                fields.push(Word {
+                    i,
+                    line,
+                    offset: start + field.len() - field.trim_start().len(),
+                    text,
+                });
+                start = offset + 1;
+            }
+            _ => {}
+        }
+    }
+    fields
+}
+
+/// A puzzle's pieces as read from a file, before they are checked against each other
+#[derive(Default)]
+struct Parts<'a> {
+    name: String,
+    difficulty: String,
+    metadata: Metadata,
+    hint_mode: HintMode,
+    col_hints: Option<(Located<'a>, Vec<usize>)>,
+    row_hints: Option<(Located<'a>, Vec<usize>)>,
+    /// The aquarium labels of each row
+    grid: Vec<Located<'a>>,
+    /// The state of each row's cells, if given
+    cells: Vec<Located<'a>>,
+    /// The depth of each aquarium's water, if given
+    levels: Option<(Located<'a>, Vec<usize>)>,
+}
+
+impl Parts<'_> {
+    fn build(self) -> Result<BatchEntry, String> {
+        let (col_line, col_hints) = self.col_hints.ok_or("Missing column hints")?;
+        let (row_line, row_hints) = self.row_hints.ok_or("Missing row hints")?;
+        let grid = self.grid;
+        if grid.is_empty() {
+            return Err("Missing grid".to_string());
+        }
+        let width = grid[0].words.len();
+        let height = grid.len();
+        for row in &grid {
+            if row.words.len() != width {
+                return Err(row.error_at_word(
+                    width,
+                    &format!("{} cells, expected {}", row.words.len(), width),
+                ));
+            }
+        }
+        if col_hints.len() != width {
+            return Err(col_line.error_at_word(
+                width,
+                &format!("{} column hints for {} columns", col_hints.len(), width),
+            ));
+        }
+        if row_hints.len() != height {
+            return Err(row_line.error_at_word(
+                height,
+                &format!("{} row hints for {} rows", row_hints.len(), height),
+            ));
+        }
+        if let Some(ix) = col_hints.iter().position(|&hint| hint > height) {
+            return Err(col_line.error_at_word(ix, "the hint is more than the height"));
+        }
+        if let Some(iy) = row_hints.iter().position(|&hint| hint > width) {
+            return Err(row_line.error_at_word(iy, "the hint is more than the width"));
+        }
+        let exact = self.hint_mode == HintMode::Exact;
+        if exact && col_hints.iter().sum::<usize>() != row_hints.iter().sum::<usize>() {
+            return Err(row_line.error_at_word(
+                0,
+                &format!(
+                    "the row hints add up to {}, but the column hints to {}",
+                    row_hints.iter().sum::<usize>(),
+                    col_hints.iter().sum::<usize>()
+                ),
+            ));
+        }
+
+        let mut board = Board::make(width, height);
+        let mut ids = HashMap::new();
+        for (iy, row) in grid.iter().enumerate() {
+            for (ix, word) in row.words.iter().enumerate() {
+                if let Some(problem) = label_problem(&word.text) {
+                    return Err(word.error(problem));
+                }
+                let next_id = ids.len();
+                let partition = *ids.entry(&*word.text).or_insert_with(|| {
+                    board.partition_labels.push(word.text.to_string());
+                    next_id
+                });
+                board.cells[iy * width + ix].partition = partition;
+            }
+        }
+        let hint = |hint: &usize| isize::try_from(*hint).unwrap();
+        board.col_hints = col_hints.iter().map(hint).collect();
+        board.row_hints = row_hints.iter().map(hint).collect();
+        board.hint_mode = self.hint_mode;
+        board.index_partitions();
+        if !self.cells.is_empty() {
+            set_cells(&mut board, &self.cells)?;
+        }
+        if let Some((line, depths)) = self.levels {
+            set_levels(&mut board, &line, &depths)?;
+        }
+
+        Ok(BatchEntry {
+            name: self.name,
+            difficulty: self.difficulty,
+            metadata: self.metadata,
+            board,
+        })
+    }
+}
+
+/// Fill each aquarium to the depth the `levels:` line gives it
+fn set_levels(board: &mut Board, line: &Located, depths: &[usize]) -> Result<(), String> {
+    if depths.len() != board.n_partitions {
+        return Err(line.error_at_word(
+            depths.len().min(board.n_partitions),
+            &format!(
+                "{} levels for {} aquariums",
+                depths.len(),
+                board.n_partitions
+            ),
+        ));
+    }
+    for (partition, &depth) in depths.iter().enumerate() {
+        board
+            .set_depth(partition, depth)
+            .map_err(|err| line.words[partition].error(&err))?;
+    }
+    Ok(())
+}
+
+/// Set the state of each cell from the `cells:` lines, one per row, then the cells their
+/// aquariums' water levels decide along with them
+fn set_cells(board: &mut Board, rows: &[Located]) -> Result<(), String> {
+    if rows.len() != board.height {
+        let last = rows.last().unwrap();
+        return Err(last.error_at_word(
+            last.words.len(),
+            &format!("{} rows of cells for {} rows", rows.len(), board.height),
+        ));
+    }
+    for (iy, row) in rows.iter().enumerate() {
+        // The states can be written with or without spaces between them
+        let states: Vec<_> = row
+            .words
+            .iter()
+            .flat_map(|word| {
+                word.text
+                    .char_indices()
+                    .map(move |(i, state)| (word.offset + i, state))
+            })
+            .collect();
+        if states.len() != board.width {
+            let offset = match states.get(board.width) {
+                Some(&(offset, _)) => offset,
+                None => row.line.trim_end().len(),
+            };
+            return Err(error_at(
+                row.i,
+                row.line,
+                offset,
+                &format!("{} cells, expected {}", states.len(), board.width),
+            ));
+        }
+        for (ix, &(offset, state)) in states.iter().enumerate() {
+            let state = match state {
+                '*' => CellState::Flooded,
+                'X' | 'x' => CellState::Invalid,
+                '.' => CellState::Empty,
+                _ => {
+                    return Err(error_at(
+                        row.i,
+                        row.line,
+                        offset,
+                        &format!(
+                            "'{}' is not a cell state; use '*' for water, 'X' for dry or '.' for undecided",
+                            state
+                        ),
+                    ))
+                }
+            };
+            board.set_cell_at(ix, iy, state);
+        }
+    }
+    board.settle_levels()
+}
+
+/// A word of a line, remembering where it was for error messages
+struct Word<'a> {
+    /// The line's number, from 0
+    i: usize,
+    line: &'a str,
+    /// The byte offset of the word in the line
+    offset: usize,
+    text: Cow<'a, str>,
+}
+
+/// Why `label` can't label an aquarium, if it can't: the grid row it's written in has to read back
+/// as a row
+fn label_problem(label: &str) -> Option<&'static str> {
+    if label.is_empty() {
+        Some("expected an aquarium's label")
+    } else if label.contains(char::is_whitespace) {
+        Some("a label can't contain spaces")
+    } else if label.contains(':') {
+        Some("a label can't contain ':'")
+    } else if label.starts_with('#') {
+        Some("a label can't start with '#'")
+    } else {
+        None
+    }
+}
+
+impl Word<'_> {
+    fn error(&self, message: &str) -> String {
+        error_at(self.i, self.line, self.offset, message)
+    }
+}
+
+/// A run of words, usually part of a single line
+struct Located<'a> {
+    /// The line the words end on, numbered from 0
+    i: usize,
+    line: &'a str,
+    words: Vec<Word<'a>>,
+}
+
+impl<'a> Located<'a> {
+    /// The words of `line` from byte `from` on
+    fn new(i: usize, line: &'a str, from: usize) -> Located<'a> {
+        let mut words = Vec::new();
+        let mut start = None;
+        let mut push = |begin: usize, end: usize| {
+            words.push(Word {
+                i,
+                line,
+                offset: begin,
+                text: Cow::Borrowed(&line[begin..end]),
+            })
+        };
+        for (offset, c) in line[from..].char_indices() {
+            let offset = from + offset;
+            match (c.is_whitespace(), start) {
+                (true, Some(begin)) => {
+                    push(begin, offset);
+                    start = None;
+                }
+                (false, None) => start = Some(offset),
+                _ => {}
+            }
+        }
+        if let Some(begin) = start {
+            push(begin, line.len());
+        }
+        Located { i, line, words }
+    }
+
+    /// The words as numbers, along with where they came from
+    fn numbers(self) -> Result<(Located<'a>, Vec<usize>), String> {
+        let numbers = self
+            .words
+            .iter()
+            .map(|word| {
+                word.text
+                    .parse()
+                    .map_err(|_| word.error(&format!("'{}' is not a number", word.text)))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok((self, numbers))
+    }
+
+    /// An error pointing at the word at `index`, or just past the end if there are fewer
+    fn error_at_word(&self, index: usize, message: &str) -> String {
+        match self.words.get(index) {
+            Some(word) => word.error(message),
+            None => error_at(self.i, self.line, self.line.trim_end().len(), message),
+        }
+    }
+}
+
+/// An error at byte `offset` of line `i`, counting from 0, quoting the line with a caret under
+/// the spot:
+///
+/// ```text
+/// Line 9, column 9: 'x' is not a number
+///    9 | rows: 2 x 3 2 1 4
+///      |         ^
+/// ```
+fn error_at(i: usize, line: &str, offset: usize, message: &str) -> String {
+    let number = (i + 1).to_string();
+    let column = line[..offset].chars().count();
+    format!(
+        "Line {}, column {}: {}\n{:>4} | {}\n{:>4} | {}^",
+        number,
+        column + 1,
+        message,
+        number,
+        line.replace('\t', " "),
+        "",
+        " ".repeat(column)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The aquarium label of every cell, row by row
+    fn layout(board: &Board) -> Vec<Vec<String>> {
+        (0..board.height)
+            .map(|iy| {
+                (0..board.width)
+                    .map(|ix| board.partition_label(board.partition_at(ix, iy)))
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn assert_same_puzzle(read: &Board, written: &Board) {
+        assert_eq!(read.col_hints, written.col_hints);
+        assert_eq!(read.row_hints, written.row_hints);
+        assert_eq!(read.hint_mode, written.hint_mode);
+        assert_eq!(layout(read), layout(written));
+    }
+
+    #[test]
+    fn a_puzzle_reads_back_from_text() {
+        for entry in crate::cli::builtin_puzzles() {
+            let text = to_text(&entry);
+            let read = parse(&text).unwrap();
+            assert_eq!(read.name, entry.name);
+            assert_eq!(read.difficulty, entry.difficulty);
+            assert_eq!(read.metadata.source, entry.metadata.source);
+            assert_eq!(read.metadata.id, entry.metadata.id);
+            assert_same_puzzle(&read.board, &entry.board);
+            assert_eq!(to_text(&read), text);
+        }
+    }
+
+    #[test]
+    fn a_solution_reads_back_from_its_levels() {
+        let mut entry = crate::cli::builtin_puzzles().remove(0);
+        crate::search::solve(&mut entry.board);
+        assert!(entry.board.is_solved());
+        let text = to_text(&entry);
+        assert!(text.contains("levels: "));
+        let read = parse(&text).unwrap();
+        assert_eq!(read.board.cells, entry.board.cells);
+    }
+
+    #[test]
+    fn labels_and_hint_mode_read_back_from_text() {
+        let text = "hints: at-least\ncols: 1 1 0\nrows: 1 1\nA -1 -1\nA bb 7\n";
+        let entry = parse(text).unwrap();
+        assert_eq!(entry.board.hint_mode, HintMode::AtLeast);
+        let read = parse(&to_text(&entry)).unwrap();
+        assert_same_puzzle(&read.board, &entry.board);
+        assert_eq!(layout(&read.board)[1], ["A", "bb", "7"]);
+    }
+
+    #[test]
+    fn labels_that_wouldnt_read_back_are_rejected() {
+        let text = |row: &str| format!("cols: 1 1\nrows: 1 1\na a\n{}\n", row);
+        assert!(parse(&text("a #b"))
+            .err()
+            .unwrap()
+            .contains("can't start with '#'"));
+        let csv = |label: &str| parse_csv(&format!(",1,1\n1,a,a\n1,a,{}\n", label));
+        assert!(csv("#b").err().unwrap().contains("can't start with '#'"));
+        assert!(csv("b:c").err().unwrap().contains("can't contain ':'"));
+        assert!(csv("b c").err().unwrap().contains("can't contain spaces"));
+        assert!(csv("b").is_ok());
+    }
+
+    #[test]
+    fn a_puzzle_reads_back_from_csv() {
+        let board = Board::make_b0();
+        let read = parse_csv(&to_csv(&board)).unwrap();
+        assert_same_puzzle(&read.board, &board);
+
+        // Labels holding commas or quotes are quoted
+        let entry = parse("cols: 1 1\nrows: 1 1\nx,y \"q\"\nx,y x,y\n").unwrap();
+        let csv = to_csv(&entry.board);
+        assert!(csv.contains("\"x,y\"") && csv.contains("\"\"\"q\"\"\""));
+        let read = parse_csv(&csv).unwrap();
+        assert_same_puzzle(&read.board, &entry.board);
+    }
+}
@@ -0,0 +1,258 @@
+//! The puzzle file formats, and how to tell which one a file is in.
+//!
+//! Each format says whether a file looks like one of its own, reads every puzzle from it, at once
+//! or one at a time, and writes puzzles back out. `FORMATS` lists them all, most particular first,
+//! so a new format only needs an entry there to be read by every command and picked with
+//! `--format`.
+
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::Path;
+
+use crate::binary;
+use crate::format;
+use crate::image;
+use crate::stats::{BatchEntry, Metadata};
+use crate::Board;
+
+/// The puzzles of a file, read as they're needed
+pub type Puzzles = Box<dyn Iterator<Item = Result<BatchEntry, String>>>;
+
+pub trait PuzzleFormat: Sync {
+    /// The name given to `--format`
+    fn name(&self) -> &'static str;
+
+    /// The file extensions the format is written with, without the dot
+    fn extensions(&self) -> &'static [&'static str];
+
+    /// Whether the file at `path`, starting with `bytes`, is in this format.
+    /// By default, whether it has one of the format's extensions.
+    fn detect(&self, path: &Path, bytes: &[u8]) -> bool {
+        let _ = bytes;
+        has_extension(path, self.extensions())
+    }
+
+    /// Read every puzzle in the file at `path`, whose contents are `bytes`
+    fn parse(&self, path: &Path, bytes: &[u8]) -> Result<Vec<BatchEntry>, String>;
+
+    /// Read the puzzles in the file at `path` from `reader` one at a time, so a file too big to
+    /// hold in memory can be read. By default the whole file is read and parsed at once, which
+    /// suits the formats holding a single puzzle.
+    fn stream(&self, path: &Path, mut reader: BufReader<File>) -> Result<Puzzles, String> {
+        let mut bytes = Vec::new();
+        reader
+            .read_to_end(&mut bytes)
+            .map_err(|err| err.to_string())?;
+        Ok(Box::new(self.parse(path, &bytes)?.into_iter().map(Ok)))
+    }
+
+    /// Write the puzzles as the contents of a file
+    fn emit(&self, entries: &[BatchEntry]) -> Result<Vec<u8>, String>;
+}
+
+/// Every format, in the order they are tried when reading a file. Text comes last, as it takes
+/// any file the others don't.
+pub const FORMATS: &[&dyn PuzzleFormat] = &[&Binary, &Image, &Janko, &Csv, &Text];
+
+/// The format called `name`
+pub fn by_name(name: &str) -> Result<&'static dyn PuzzleFormat, String> {
+    FORMATS
+        .iter()
+        .copied()
+        .find(|format| format.name() == name)
+        .ok_or_else(|| {
+            let names: Vec<_> = FORMATS.iter().map(|format| format.name()).collect();
+            format!(
+                "Unknown format {}; expected auto or one of {}",
+                name,
+                names.join(", ")
+            )
+        })
+}
+
+/// The format named by a `--format` argument, or `None` for `auto`
+pub fn from_arg(arg: &str) -> Result<Option<&'static dyn PuzzleFormat>, String> {
+    match arg {
+        "auto" => Ok(None),
+        name => by_name(name).map(Some),
+    }
+}
+
+/// The format a file is in, going by its contents and then its extension
+pub fn detect(path: &Path, bytes: &[u8]) -> &'static dyn PuzzleFormat {
+    FORMATS
+        .iter()
+        .copied()
+        .find(|format| format.detect(path, bytes))
+        .unwrap_or(&Text)
+}
+
+/// The format to write a file in, going by its extension
+pub fn for_extension(path: &Path) -> Option<&'static dyn PuzzleFormat> {
+    FORMATS
+        .iter()
+        .copied()
+        .find(|format| has_extension(path, format.extensions()))
+}
+
+fn has_extension(path: &Path, extensions: &[&str]) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| extensions.contains(&extension))
+}
+
+fn utf8(bytes: &[u8]) -> Result<&str, String> {
+    std::str::from_utf8(bytes).map_err(|err| format!("The file isn't text: {}", err))
+}
+
+/// The text format; see the `format` module
+pub struct Text;
+
+impl PuzzleFormat for Text {
+    fn name(&self) -> &'static str {
+        "text"
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &["txt"]
+    }
+
+    fn detect(&self, _path: &Path, _bytes: &[u8]) -> bool {
+        true
+    }
+
+    fn parse(&self, _path: &Path, bytes: &[u8]) -> Result<Vec<BatchEntry>, String> {
+        format::parse_all(utf8(bytes)?)
+    }
+
+    fn stream(&self, _path: &Path, reader: BufReader<File>) -> Result<Puzzles, String> {
+        Ok(Box::new(format::TextPuzzles::new(reader)))
+    }
+
+    fn emit(&self, entries: &[BatchEntry]) -> Result<Vec<u8>, String> {
+        let texts: Vec<_> = entries.iter().map(format::to_text).collect();
+        Ok(texts.join("\n").into_bytes())
+    }
+}
+
+/// One puzzle as CSV; see `format::parse_csv`
+pub struct Csv;
+
+impl PuzzleFormat for Csv {
+    fn name(&self) -> &'static str {
+        "csv"
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &["csv"]
+    }
+
+    fn parse(&self, _path: &Path, bytes: &[u8]) -> Result<Vec<BatchEntry>, String> {
+        Ok(vec![format::parse_csv(utf8(bytes)?)?])
+    }
+
+    fn emit(&self, entries: &[BatchEntry]) -> Result<Vec<u8>, String> {
+        match entries {
+            [entry] => Ok(format::to_csv(&entry.board).into_bytes()),
+            _ => Err(format!(
+                "A CSV file holds one puzzle, not {}",
+                entries.len()
+            )),
+        }
+    }
+}
+
+/// One puzzle in the janko.at archive's format; see `format::parse_janko`. The lines before the
+/// first section are skipped, so a page saved from the site reads the same as the puzzle alone.
+pub struct Janko;
+
+impl PuzzleFormat for Janko {
+    fn name(&self) -> &'static str {
+        "janko"
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &["janko"]
+    }
+
+    fn detect(&self, path: &Path, bytes: &[u8]) -> bool {
+        // Section names are read in any case
+        let text = String::from_utf8_lossy(bytes).to_ascii_lowercase();
+        has_extension(path, self.extensions())
+            || (text.contains("[problem]") && text.contains("[areas]"))
+    }
+
+    fn parse(&self, _path: &Path, bytes: &[u8]) -> Result<Vec<BatchEntry>, String> {
+        Ok(vec![format::parse_janko(utf8(bytes)?)?])
+    }
+
+    fn emit(&self, _entries: &[BatchEntry]) -> Result<Vec<u8>, String> {
+        Err("Puzzles can be read in the janko.at format, but not written in it".to_string())
+    }
+}
+
+/// One puzzle painted as a PNG, with its hints in a sidecar file; see the `image` module
+pub struct Image;
+
+impl PuzzleFormat for Image {
+    fn name(&self) -> &'static str {
+        "png"
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &["png"]
+    }
+
+    fn detect(&self, path: &Path, bytes: &[u8]) -> bool {
+        bytes.starts_with(b"\x89PNG\r\n\x1a\n") || has_extension(path, self.extensions())
+    }
+
+    fn parse(&self, path: &Path, _bytes: &[u8]) -> Result<Vec<BatchEntry>, String> {
+        Ok(vec![image::read(path, None)?])
+    }
+
+    fn emit(&self, _entries: &[BatchEntry]) -> Result<Vec<u8>, String> {
+        Err("Puzzles can be read from images, but not written as them".to_string())
+    }
+}
+
+/// Any number of puzzles in the compact binary form; see the `binary` module
+pub struct Binary;
+
+impl PuzzleFormat for Binary {
+    fn name(&self) -> &'static str {
+        "aqb"
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &["aqb"]
+    }
+
+    fn detect(&self, path: &Path, bytes: &[u8]) -> bool {
+        bytes.starts_with(binary::MAGIC) || has_extension(path, self.extensions())
+    }
+
+    fn parse(&self, _path: &Path, bytes: &[u8]) -> Result<Vec<BatchEntry>, String> {
+        Ok(binary::read(bytes)?.into_iter().map(unnamed).collect())
+    }
+
+    fn stream(&self, _path: &Path, reader: BufReader<File>) -> Result<Puzzles, String> {
+        let records = binary::Records::new(reader)?;
+        Ok(Box::new(records.map(|board| board.map(unnamed))))
+    }
+
+    fn emit(&self, entries: &[BatchEntry]) -> Result<Vec<u8>, String> {
+        let boards: Vec<_> = entries.iter().map(|entry| &entry.board).collect();
+        binary::write(&boards)
+    }
+}
+
+/// An entry for a board read without a name or metadata
+fn unnamed(board: Board) -> BatchEntry {
+    BatchEntry {
+        name: String::new(),
+        difficulty: String::new(),
+        metadata: Metadata::default(),
+        board,
+    }
+}
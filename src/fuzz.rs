@@ -0,0 +1,387 @@
+//! Random and deliberately broken puzzles fed through the readers, the rules and the search, to
+//! shake out panics, hangs and unsound deductions.
+//!
+//! Each case is a generated puzzle written out in one of the file formats, sometimes with hints
+//! knocked off their true values, cells decided beforehand, the hints made lower bounds, or the
+//! bytes of the file cut, repeated and scrambled. Every case runs on its own thread, so a panic or
+//! a hang is caught and reported rather than ending the run. A puzzle that reads must come through
+//! the rules without a deduction contradicting a solution the search finds, and the search and the
+//! rules must agree on whether there is a solution at all.
+//!
+//! A failing case is shrunk by cutting out runs of bytes for as long as it keeps failing the same
+//! way, and the smallest one found is written to disk.
+
+use std::any::Any;
+use std::fmt;
+use std::fs;
+use std::panic;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc};
+use std::thread;
+use std::time::Duration;
+
+use crate::formats::{self, PuzzleFormat};
+use crate::generate::{self, Rng};
+use crate::search::{self, Heuristic};
+use crate::stats::{BatchEntry, Metadata};
+use crate::{binary, format, oracle, Board, CellState, HintMode};
+
+/// The most cases a failure is run again while shrinking it
+const MAX_SHRINK_RUNS: usize = 400;
+
+pub struct Options {
+    pub iterations: usize,
+    pub seed: u64,
+    /// The widest and tallest board generated
+    pub max_size: usize,
+    /// How long a case may run before it counts as a hang
+    pub timeout: Duration,
+    /// Where failing cases are written
+    pub out: PathBuf,
+}
+
+/// How a case failed
+#[derive(Clone, Debug)]
+pub enum Failure {
+    Panic(String),
+    Hang,
+    /// A rule decided a cell against a solution the search found
+    Unsound(String),
+    /// The rules and the search disagree about whether the puzzle has a solution
+    Disagreement(String),
+}
+
+impl Failure {
+    fn name(&self) -> &'static str {
+        match self {
+            Failure::Panic(_) => "panic",
+            Failure::Hang => "hang",
+            Failure::Unsound(_) => "unsound",
+            Failure::Disagreement(_) => "disagreement",
+        }
+    }
+
+    fn same_kind(&self, other: &Failure) -> bool {
+        self.name() == other.name()
+    }
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Failure::Panic(message) => write!(f, "panic: {}", message),
+            Failure::Hang => write!(f, "hang"),
+            Failure::Unsound(message) => write!(f, "unsound deduction: {}", message),
+            Failure::Disagreement(message) => write!(f, "disagreement: {}", message),
+        }
+    }
+}
+
+/// A failing case, after shrinking
+pub struct Found {
+    pub iteration: usize,
+    pub failure: Failure,
+    /// Where the case was written
+    pub path: PathBuf,
+}
+
+/// What a run came to
+#[derive(Default)]
+pub struct Summary {
+    pub cases: usize,
+    /// Cases that read as at least one puzzle
+    pub parsed: usize,
+    pub found: Vec<Found>,
+}
+
+/// Run the cases, writing each failure found to `options.out`. Panics are caught and reported
+/// rather than printed while it runs.
+pub fn run(options: &Options) -> Result<Summary, String> {
+    let hook = panic::take_hook();
+    panic::set_hook(Box::new(|_| {}));
+    let summary = run_cases(options);
+    panic::set_hook(hook);
+    summary
+}
+
+fn run_cases(options: &Options) -> Result<Summary, String> {
+    let mut rng = Rng::new(options.seed);
+    let mut summary = Summary::default();
+    for iteration in 0..options.iterations {
+        let (format, bytes) = case(&mut rng, options.max_size);
+        summary.cases += 1;
+        let outcome = check(format, &bytes, options.timeout);
+        match outcome {
+            Ok(true) => summary.parsed += 1,
+            Ok(false) => {}
+            Err(failure) => {
+                let (bytes, failure) = shrink(format, bytes, failure, options.timeout);
+                fs::create_dir_all(&options.out).map_err(|err| {
+                    format!("Could not create {}: {}", options.out.display(), err)
+                })?;
+                let path = options.out.join(format!(
+                    "{}-{}.{}",
+                    failure.name(),
+                    iteration,
+                    format.extensions()[0]
+                ));
+                fs::write(&path, &bytes)
+                    .map_err(|err| format!("Could not write {}: {}", path.display(), err))?;
+                summary.found.push(Found {
+                    iteration,
+                    failure,
+                    path,
+                });
+            }
+        }
+    }
+    Ok(summary)
+}
+
+/// A generated puzzle, perhaps altered, written out in a random format
+fn case(rng: &mut Rng, max_size: usize) -> (&'static dyn PuzzleFormat, Vec<u8>) {
+    let width = 1 + rng.below(max_size);
+    let height = 1 + rng.below(max_size);
+    let solution = generate::generate(width, height, rng);
+    let mut board = solution.clone();
+    board.clear();
+
+    // Knock a hint off its true value, which may leave the puzzle with no solution
+    if rng.below(4) == 0 {
+        let line = rng.below(width + height);
+        let by = 1 + rng.below(3) as isize;
+        let hint = if line < height {
+            &mut board.row_hints[line]
+        } else {
+            &mut board.col_hints[line - height]
+        };
+        *hint = if rng.below(2) == 0 {
+            (*hint - by).max(0)
+        } else {
+            *hint + by
+        };
+    }
+    // Decide some cells beforehand, now and then wrongly
+    let mut cells = None;
+    if rng.below(4) == 0 {
+        let wrong = rng.below(4) == 0;
+        let states: Vec<_> = solution
+            .cells
+            .iter()
+            .map(|cell| match rng.below(3) {
+                0 if wrong && rng.below(2) == 0 => CellState::Flooded,
+                0 => cell.state,
+                _ => CellState::Empty,
+            })
+            .collect();
+        cells = Some(states);
+    }
+    if rng.below(8) == 0 {
+        board.hint_mode = HintMode::AtLeast;
+    }
+
+    let entry = BatchEntry {
+        name: String::new(),
+        difficulty: String::new(),
+        metadata: Metadata::default(),
+        board,
+    };
+    let (name, mut bytes) = match rng.below(4) {
+        0 => ("csv", format::to_csv(&entry.board).into_bytes()),
+        1 => ("janko", janko(&entry.board).into_bytes()),
+        2 if entry.board.hint_mode == HintMode::Exact => (
+            "aqb",
+            binary::write(&[&entry.board]).expect("A generated board doesn't pack"),
+        ),
+        _ => {
+            let mut text = format::to_text(&entry);
+            if let Some(states) = &cells {
+                for row in states.chunks(width) {
+                    let row: String = row.iter().map(|state| state_char(*state)).collect();
+                    text += &format!("cells: {}\n", row);
+                }
+            }
+            ("text", text.into_bytes())
+        }
+    };
+    if rng.below(2) == 0 {
+        for _ in 0..1 + rng.below(4) {
+            mutate(&mut bytes, rng);
+        }
+    }
+    (formats::by_name(name).unwrap(), bytes)
+}
+
+fn state_char(state: CellState) -> char {
+    match state {
+        CellState::Empty => '.',
+        CellState::Flooded => '*',
+        CellState::Invalid => 'X',
+    }
+}
+
+/// The board in the janko.at format, clues along the top and left
+fn janko(board: &Board) -> String {
+    let mut out = String::from("[problem]\n-");
+    for hint in &board.col_hints {
+        out += &format!(" {}", hint);
+    }
+    out.push('\n');
+    for hint in &board.row_hints {
+        out += &hint.to_string();
+        out += &" -".repeat(board.width);
+        out.push('\n');
+    }
+    out += "[areas]\n";
+    for iy in 0..board.height {
+        let row: Vec<_> = (0..board.width)
+            .map(|ix| board.partition_label(board.partition_at(ix, iy)))
+            .collect();
+        out += &row.join(" ");
+        out.push('\n');
+    }
+    out += "[end]\n";
+    out
+}
+
+/// Cut, repeat, overwrite or insert a few bytes, favouring the ones the formats care about
+fn mutate(bytes: &mut Vec<u8>, rng: &mut Rng) {
+    const INTERESTING: &[u8] = b"0123456789 -\n:*X.,[]#";
+    let at = rng.below(bytes.len() + 1);
+    let len = 1 + rng.below(8);
+    let end = (at + len).min(bytes.len());
+    match rng.below(5) {
+        0 => {
+            bytes.drain(at..end);
+        }
+        1 => {
+            let copy = bytes[at..end].to_vec();
+            bytes.splice(at..at, copy);
+        }
+        2 if at < bytes.len() => bytes[at] = rng.next_u64() as u8,
+        3 if at < bytes.len() => bytes[at] = INTERESTING[rng.below(INTERESTING.len())],
+        _ => bytes.insert(at, INTERESTING[rng.below(INTERESTING.len())]),
+    }
+}
+
+/// Run a case on its own thread, giving up on it after `timeout`.
+/// Ok with whether it read as a puzzle, or how it failed.
+fn check(
+    format: &'static dyn PuzzleFormat,
+    bytes: &[u8],
+    timeout: Duration,
+) -> Result<bool, Failure> {
+    let cancelled = Arc::new(AtomicBool::new(false));
+    let (sender, receiver) = mpsc::channel();
+    let bytes = bytes.to_vec();
+    let stop = Arc::clone(&cancelled);
+    let handle = thread::spawn(move || {
+        let _ = sender.send(check_case(format, &bytes, &stop));
+    });
+    match receiver.recv_timeout(timeout) {
+        Ok(outcome) => {
+            let _ = handle.join();
+            outcome
+        }
+        Err(mpsc::RecvTimeoutError::Timeout) => {
+            // Stop the solve if that is where it's stuck; a stuck reader is left to run
+            cancelled.store(true, Ordering::Relaxed);
+            Err(Failure::Hang)
+        }
+        Err(mpsc::RecvTimeoutError::Disconnected) => match handle.join() {
+            Err(panic) => Err(Failure::Panic(panic_message(panic))),
+            Ok(()) => unreachable!("The case finished without sending its outcome"),
+        },
+    }
+}
+
+fn panic_message(panic: Box<dyn Any + Send>) -> String {
+    match panic.downcast::<String>() {
+        Ok(message) => *message,
+        Err(panic) => match panic.downcast::<&str>() {
+            Ok(message) => message.to_string(),
+            Err(_) => "unknown panic".to_string(),
+        },
+    }
+}
+
+/// Read the case and put every puzzle in it through the rules and the search
+fn check_case(
+    format: &dyn PuzzleFormat,
+    bytes: &[u8],
+    cancelled: &AtomicBool,
+) -> Result<bool, Failure> {
+    let path = Path::new("fuzz").with_extension(format.extensions()[0]);
+    let Ok(entries) = format.parse(&path, bytes) else {
+        return Ok(false);
+    };
+    for entry in entries {
+        let mut solution = entry.board.clone();
+        let (found, stats) = search::search_observed(
+            &mut solution,
+            Heuristic::default(),
+            &mut search::Cancellation(cancelled),
+        );
+        if stats.stopped {
+            return Err(Failure::Hang);
+        }
+
+        let mut rules = entry.board.clone();
+        rules.solve_observed(&mut Vec::new(), &mut search::Cancellation(cancelled));
+        if rules.is_solved() && !found {
+            return Err(Failure::Disagreement(
+                "the rules solved a puzzle the search found no solution to".to_string(),
+            ));
+        }
+        if !found {
+            continue;
+        }
+        if !solution.is_solved() {
+            return Err(Failure::Disagreement(
+                "the search reported a solution that doesn't meet the hints".to_string(),
+            ));
+        }
+        match oracle::check(&entry.board, &solution) {
+            Ok((_, None)) => {}
+            Ok((_, Some(contradiction))) => {
+                return Err(Failure::Unsound(format!(
+                    "{}: {}",
+                    contradiction.deduction, contradiction.deduction.reason
+                )))
+            }
+            Err(err) => return Err(Failure::Disagreement(err)),
+        }
+    }
+    Ok(true)
+}
+
+/// Cut runs of bytes out of a failing case, halving the runs' length each time through, for as
+/// long as it keeps failing the same way
+fn shrink(
+    format: &'static dyn PuzzleFormat,
+    mut bytes: Vec<u8>,
+    mut failure: Failure,
+    timeout: Duration,
+) -> (Vec<u8>, Failure) {
+    let mut runs = 0;
+    let mut chunk = bytes.len() / 2;
+    while chunk > 0 && runs < MAX_SHRINK_RUNS {
+        let mut at = 0;
+        while at < bytes.len() && runs < MAX_SHRINK_RUNS {
+            let end = (at + chunk).min(bytes.len());
+            let mut smaller = bytes[..at].to_vec();
+            smaller.extend_from_slice(&bytes[end..]);
+            runs += 1;
+            match check(format, &smaller, timeout) {
+                Err(again) if again.same_kind(&failure) => {
+                    bytes = smaller;
+                    failure = again;
+                }
+                _ => at += chunk,
+            }
+        }
+        chunk /= 2;
+    }
+    (bytes, failure)
+}
@@ -0,0 +1,117 @@
+//! Random puzzles from a seed.
+//!
+//! The board is cut into random connected aquariums, each aquarium is filled to a random level,
+//! and the hints are counted off the result. The same seed and size give the same puzzle on every
+//! machine. Nothing checks that the puzzle has only one solution.
+
+use crate::{Board, CellState};
+
+/// SplitMix64: small, fast and the same everywhere, which is all the generator needs
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Rng {
+        Rng { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// A number in 0..n
+    pub fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+}
+
+/// The most cells an aquarium is grown to
+const MAX_AQUARIUM: usize = 8;
+
+/// A random puzzle of the given size, with its solution filled in; call `clear` on it to get the
+/// puzzle
+pub fn generate(width: usize, height: usize, rng: &mut Rng) -> Board {
+    let mut board = Board::make(width, height);
+    lay_out(&mut board, rng);
+
+    for partition in board.partitions() {
+        let rows: Vec<_> = (0..height)
+            .filter(|&iy| (0..width).any(|ix| board.partition_at(ix, iy) == partition))
+            .collect();
+        // One choice per row the aquarium reaches, and one for leaving it unfilled
+        let choice = rng.below(rows.len() + 1);
+        if let Some(&level) = rows.get(choice) {
+            board
+                .set_water_level(partition, Some(level))
+                .expect("An aquarium rejected one of its own rows");
+        } else {
+            board
+                .set_water_level(partition, None)
+                .expect("An aquarium rejected being unfilled");
+        }
+    }
+
+    for iy in 0..height {
+        board.row_hints[iy] = board.flooded_rows.count(iy);
+    }
+    for ix in 0..width {
+        board.col_hints[ix] = board.flooded_cols.count(ix);
+    }
+    board
+}
+
+/// Cut the board into connected aquariums of 1 to `MAX_AQUARIUM` cells.
+/// Each aquarium starts from the first cell not yet taken, in reading order,
+/// and grows onto random untaken neighbours.
+fn lay_out(board: &mut Board, rng: &mut Rng) {
+    let (width, height) = (board.width, board.height);
+    let mut taken = vec![false; width * height];
+    let mut next_partition = 0;
+
+    for start in 0..width * height {
+        if taken[start] {
+            continue;
+        }
+        let size = 1 + rng.below(MAX_AQUARIUM);
+        let mut cells = vec![start];
+        taken[start] = true;
+        let mut frontier = Vec::new();
+        while cells.len() < size {
+            frontier.clear();
+            for &i in &cells {
+                let (ix, iy) = (i % width, i / width);
+                if ix > 0 {
+                    frontier.push(i - 1);
+                }
+                if ix + 1 < width {
+                    frontier.push(i + 1);
+                }
+                if iy > 0 {
+                    frontier.push(i - width);
+                }
+                if iy + 1 < height {
+                    frontier.push(i + width);
+                }
+            }
+            frontier.retain(|&i| !taken[i]);
+            if frontier.is_empty() {
+                break;
+            }
+            let next = frontier[rng.below(frontier.len())];
+            taken[next] = true;
+            cells.push(next);
+        }
+
+        for i in cells {
+            board.cells[i].partition = next_partition;
+            board.cells[i].state = CellState::Empty;
+        }
+        next_partition += 1;
+    }
+    board.index_partitions();
+}
@@ -0,0 +1,92 @@
+//! Saved rule traces, to tell whether a change to the rules changed what they deduce.
+//!
+//! A trace file holds a `[name]` line for each puzzle, followed by its deductions in order, one per
+//! line as `pass rule: verb ix, iy`:
+//!
+//! ```text
+//! [b0]
+//! 1 R1: Invalidate 0, 4
+//! 1 R2: Flood 4, 0
+//! ```
+
+use std::path::Path;
+
+use crate::stats::BatchEntry;
+
+/// The deductions the rules make on each puzzle, as the lines a trace file holds
+pub fn record(entries: &[BatchEntry]) -> Vec<(String, Vec<String>)> {
+    entries
+        .iter()
+        .map(|entry| {
+            let mut board = entry.board.clone();
+            let mut trace = Vec::new();
+            board.solve_traced(&mut trace);
+            let lines = trace
+                .iter()
+                .map(|deduction| format!("{} {}", deduction.iteration, deduction))
+                .collect();
+            (entry.name.clone(), lines)
+        })
+        .collect()
+}
+
+pub fn write(path: &Path, traces: &[(String, Vec<String>)]) -> Result<(), String> {
+    let mut text = String::new();
+    for (name, lines) in traces {
+        text += &format!("[{}]\n", name);
+        for line in lines {
+            text += line;
+            text.push('\n');
+        }
+    }
+    std::fs::write(path, text).map_err(|err| err.to_string())
+}
+
+pub fn read(path: &Path) -> Result<Vec<(String, Vec<String>)>, String> {
+    let text = std::fs::read_to_string(path).map_err(|err| err.to_string())?;
+    let mut traces: Vec<(String, Vec<String>)> = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        if let Some(name) = line.strip_prefix('[').and_then(|it| it.strip_suffix(']')) {
+            traces.push((name.to_string(), Vec::new()));
+        } else if let Some((_, lines)) = traces.last_mut() {
+            lines.push(line.to_string());
+        } else if !line.trim().is_empty() {
+            return Err(format!("Line {}: a deduction before any [name]", i + 1));
+        }
+    }
+    Ok(traces)
+}
+
+/// The first place each puzzle's new trace parts from its saved one, one message per puzzle.
+/// Empty if every puzzle deduced the same as before.
+pub fn compare(saved: &[(String, Vec<String>)], new: &[(String, Vec<String>)]) -> Vec<String> {
+    let mut divergences = Vec::new();
+    for (name, lines) in new {
+        let Some((_, saved_lines)) = saved.iter().find(|(saved_name, _)| saved_name == name) else {
+            divergences.push(format!("{}: not in the saved traces", name));
+            continue;
+        };
+        let Some(i) =
+            (0..lines.len().max(saved_lines.len())).find(|&i| lines.get(i) != saved_lines.get(i))
+        else {
+            continue;
+        };
+        let show = |line: Option<&String>| match line {
+            Some(line) => line.clone(),
+            None => "(end of trace)".to_string(),
+        };
+        divergences.push(format!(
+            "{}: deduction {} differs\n  saved: {}\n  now:   {}",
+            name,
+            i + 1,
+            show(saved_lines.get(i)),
+            show(lines.get(i))
+        ));
+    }
+    for (name, _) in saved {
+        if !new.iter().any(|(new_name, _)| new_name == name) {
+            divergences.push(format!("{}: saved, but no longer traced", name));
+        }
+    }
+    divergences
+}
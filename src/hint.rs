@@ -0,0 +1,93 @@
+//! Hints for a player: which deduction to make next from a position.
+//!
+//! Every deduction the rules can make is found, and those deciding the same cells are counted
+//! once. They can be offered in the order the solver meets them, or ranked for a player after an
+//! efficient line: by how many cells each decides, or by how many new deductions it opens up.
+
+use crate::{Board, CellState, Deduction};
+
+/// How to order the hints
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub enum Rank {
+    /// The order the solver meets them
+    First,
+    /// The most cells decided first
+    Cells,
+    /// The most new deductions opened up first
+    Unlocks,
+}
+
+impl Rank {
+    pub fn from_name(name: &str) -> Option<Rank> {
+        match name {
+            "first" => Some(Rank::First),
+            "cells" => Some(Rank::Cells),
+            "unlocks" => Some(Rank::Unlocks),
+            _ => None,
+        }
+    }
+}
+
+pub struct Hint {
+    pub deduction: Deduction,
+    /// The cells the deduction decides
+    pub cells: usize,
+    /// The aquarium rows that can be decided once it is made but couldn't before
+    pub unlocks: usize,
+}
+
+/// The deductions available on `board`, one per set of cells decided, in the order asked for.
+/// Ties keep the solver's order.
+pub fn hints(board: &Board, rank: Rank) -> Vec<Hint> {
+    let available = board.available_deductions();
+    let before = effects(board, &available);
+
+    let mut hints: Vec<Hint> = Vec::new();
+    let mut seen = Vec::new();
+    for (deduction, effect) in available.into_iter().zip(before.iter().copied()) {
+        if seen.contains(&effect) {
+            continue;
+        }
+        seen.push(effect);
+        let mut after = board.clone();
+        deduction.apply(&mut after);
+        let cells = board
+            .cells
+            .iter()
+            .zip(&after.cells)
+            .filter(|(cell, then)| cell.state != then.state)
+            .count();
+        let mut opened = Vec::new();
+        for effect in effects(&after, &after.available_deductions()) {
+            if !before.contains(&effect) && !opened.contains(&effect) {
+                opened.push(effect);
+            }
+        }
+        hints.push(Hint {
+            deduction,
+            cells,
+            unlocks: opened.len(),
+        });
+    }
+    match rank {
+        Rank::First => {}
+        Rank::Cells => hints.sort_by_key(|hint| std::cmp::Reverse(hint.cells)),
+        Rank::Unlocks => hints.sort_by_key(|hint| std::cmp::Reverse(hint.unlocks)),
+    }
+    hints
+}
+
+/// What each deduction does: the aquarium it fills or empties, the row it starts at and the state
+/// it gives
+fn effects(board: &Board, deductions: &[Deduction]) -> Vec<(usize, usize, CellState)> {
+    deductions
+        .iter()
+        .map(|deduction| {
+            (
+                board.partition_at(deduction.ix, deduction.iy),
+                deduction.iy,
+                deduction.state,
+            )
+        })
+        .collect()
+}
@@ -0,0 +1,115 @@
+//! Puzzles painted as images.
+//!
+//! Each aquarium is painted in a flat colour of its own, filling its cells edge to edge. The hints
+//! come from a sidecar file next to the image, `puzzle.hints` for `puzzle.png`, holding the `cols:`
+//! and `rows:` lines of the text format along with any name or metadata. The hints give the size
+//! of the grid, so the image is cut into that many cells and each cell takes the colour at its
+//! centre. Cells of one colour that touch make up an aquarium; separate patches of the same colour
+//! are separate aquariums.
+
+use std::fs::File;
+use std::path::Path;
+
+use crate::format;
+use crate::stats::BatchEntry;
+
+/// Read a painted puzzle, taking its hints from `hints`, or from the image's sidecar file
+pub fn read(path: &Path, hints: Option<&Path>) -> Result<BatchEntry, String> {
+    let sidecar = path.with_extension("hints");
+    let hints_path = hints.unwrap_or(&sidecar);
+    let hints = std::fs::read_to_string(hints_path)
+        .map_err(|err| format!("Could not read the hints {}: {}", hints_path.display(), err))?;
+    let count = |key: &str| {
+        hints
+            .lines()
+            .filter_map(|line| line.split_once(':'))
+            .find(|(it, _)| it.trim() == key)
+            .map(|(_, value)| value.split_whitespace().count())
+    };
+    let width = count("cols").ok_or("Missing column hints")?;
+    let height = count("rows").ok_or("Missing row hints")?;
+    if width == 0 || height == 0 {
+        return Err("The hints give an empty grid".to_string());
+    }
+
+    let grid = regions(&read_colours(path, width, height)?, width, height);
+    let mut text = hints.clone();
+    if !text.is_empty() && !text.ends_with('\n') {
+        text.push('\n');
+    }
+    for row in grid.chunks(width) {
+        let labels: Vec<_> = row.iter().map(|region| region.to_string()).collect();
+        text += &labels.join(" ");
+        text.push('\n');
+    }
+    format::parse(&text)
+}
+
+/// The colour at the centre of each of the `width` by `height` cells the image is cut into,
+/// in reading order
+fn read_colours(path: &Path, width: usize, height: usize) -> Result<Vec<Vec<u8>>, String> {
+    let file = File::open(path).map_err(|err| err.to_string())?;
+    let mut decoder = png::Decoder::new(file);
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info().map_err(|err| err.to_string())?;
+    let mut pixels = vec![0; reader.output_buffer_size()];
+    let info = reader
+        .next_frame(&mut pixels)
+        .map_err(|err| err.to_string())?;
+
+    let (image_width, image_height) = (info.width as usize, info.height as usize);
+    if image_width < width || image_height < height {
+        return Err(format!(
+            "The image is {}x{} pixels, too small for {}x{} cells",
+            image_width, image_height, width, height
+        ));
+    }
+    let samples = info.color_type.samples();
+    let mut colours = Vec::with_capacity(width * height);
+    for iy in 0..height {
+        let y = (2 * iy + 1) * image_height / (2 * height);
+        for ix in 0..width {
+            let x = (2 * ix + 1) * image_width / (2 * width);
+            let start = y * info.line_size + x * samples;
+            colours.push(pixels[start..start + samples].to_vec());
+        }
+    }
+    Ok(colours)
+}
+
+/// Number the patches of touching cells of one colour, in the order they are first met
+fn regions(colours: &[Vec<u8>], width: usize, height: usize) -> Vec<usize> {
+    let mut region = vec![None; colours.len()];
+    let mut n_regions = 0;
+    for start in 0..colours.len() {
+        if region[start].is_some() {
+            continue;
+        }
+        region[start] = Some(n_regions);
+        let mut stack = vec![start];
+        while let Some(i) = stack.pop() {
+            let (ix, iy) = (i % width, i / width);
+            let mut neighbours = Vec::with_capacity(4);
+            if ix > 0 {
+                neighbours.push(i - 1);
+            }
+            if ix + 1 < width {
+                neighbours.push(i + 1);
+            }
+            if iy > 0 {
+                neighbours.push(i - width);
+            }
+            if iy + 1 < height {
+                neighbours.push(i + width);
+            }
+            for next in neighbours {
+                if region[next].is_none() && colours[next] == colours[i] {
+                    region[next] = Some(n_regions);
+                    stack.push(next);
+                }
+            }
+        }
+        n_regions += 1;
+    }
+    region.into_iter().map(Option::unwrap).collect()
+}
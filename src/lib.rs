@@ -0,0 +1,2139 @@
+//! Aquarium puzzle solver
+//! https://www.puzzle-aquarium.com/
+//!
+//! > **Aquarium** is a logic puzzle with simple rules and challenging solutions.
+//!
+//! > The rules of Aquarium are simple:
+//! > The puzzle is played on a rectangular grid divided into blocks called "aquariums".
+//! > You have to "fill" the aquariums with water up to a certain level or leave it empty.
+//! > The water level in each aquarium is one and the same across its full width
+//! > The numbers outside the grid show the number of filled cells horizontally and vertically.
+//!
+//! An aquarium puzzle is defined by an NxM (width x height) grid of cells. (Commonly NxN).
+//! Between each cell, there may be a seperator (wall or floor) that prevents the cells from touching.
+//! Cells that touch are in the same partition.
+//! Cells can be marked:
+//! * Empty: Logically unknown required state
+//! * Flooded: Known to be required to be flooded
+//! * Invalid: Known to be incapable of being flooded
+//!
+//! Every cell in the same partition and row must have the same state.
+//! Every cell in the same partition and above an invalid cell must be invalid.
+//! Every cell in the same partition and below a  flooded cell must be flooded.
+//!
+//! This behaviour mimics filling a volume with water.
+//!
+//! Each row and column has a hint describing how many cells in the given row or column need filled.
+//!
+//! The puzzle is solved when the count of cells filled exactly matches each of the associated hints
+//! and the rules describing cell behaviour are followed.
+//!
+//! Note: Cells can be in the same row and partition without directly touching. i.e. a 'U' shape:
+//! ```text
+//! 0 1 0  // The '0's don't touch but are in the same partition and therefore must have the same state.
+//! 0 0 0
+//! ```
+//!
+//! Without the default `std` feature only the board, its rules and the search are built, on
+//! `core` and `alloc` alone, with the rule passes run one line after another instead of on
+//! rayon's threads and solves left untimed. Reading, writing and drawing puzzles and the
+//! `aquarium` command all need `std`.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+// TODO
+#![allow(dead_code)]
+#![allow(unused_variables)]
+
+extern crate alloc;
+
+mod bits;
+mod feasibility;
+pub mod observer;
+mod parallel;
+pub mod search;
+pub mod stats;
+
+#[cfg(feature = "std")]
+mod autofill;
+#[cfg(feature = "std")]
+mod bench;
+#[cfg(feature = "std")]
+mod binary;
+#[cfg(feature = "std")]
+mod campaign;
+#[cfg(feature = "std")]
+mod cast;
+#[cfg(feature = "std")]
+pub mod cli;
+#[cfg(feature = "std")]
+mod compose;
+#[cfg(feature = "std")]
+mod daily;
+#[cfg(feature = "std")]
+mod describe;
+#[cfg(feature = "std")]
+mod dot;
+#[cfg(feature = "std")]
+mod enumerate;
+#[cfg(feature = "std")]
+mod format;
+#[cfg(feature = "std")]
+mod formats;
+#[cfg(feature = "std")]
+mod fuzz;
+#[cfg(feature = "std")]
+mod generate;
+#[cfg(feature = "std")]
+mod golden;
+#[cfg(feature = "std")]
+mod hint;
+#[cfg(feature = "std")]
+mod image;
+#[cfg(feature = "std")]
+mod markdown;
+#[cfg(feature = "std")]
+mod oracle;
+#[cfg(feature = "std")]
+mod pdf;
+#[cfg(feature = "std")]
+mod render;
+#[cfg(feature = "std")]
+mod survey;
+#[cfg(feature = "std")]
+mod svg;
+#[cfg(feature = "std")]
+mod unsat;
+
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use alloc::{format, vec};
+use core::convert::TryFrom;
+use core::fmt::{self, Write};
+use core::ops::ControlFlow;
+
+use observer::SolverObserver;
+use parallel::*;
+#[cfg(feature = "std")]
+use render::{RenderMode, RenderOptions};
+use stats::SolveStats;
+/// Valid transitions: Empty->Flooded, Empty->Invalid
+#[derive(PartialEq, Eq, Hash, Copy, Clone, Debug)]
+pub enum CellState {
+    Empty,
+    Flooded,
+    Invalid,
+}
+
+impl CellState {
+    fn rep(&self) -> char {
+        match self {
+            CellState::Empty => ' ',
+            CellState::Flooded => '*',
+            CellState::Invalid => 'X',
+        }
+    }
+}
+
+#[derive(PartialEq, Copy, Clone)]
+enum WallState {
+    None,
+    Wall,
+}
+
+impl WallState {
+    fn rep(&self) -> char {
+        use WallState::*;
+        match self {
+            None => '|',
+            Wall => '#',
+        }
+    }
+
+    fn from_bool(state: bool) -> WallState {
+        match state {
+            true => WallState::Wall,
+            false => WallState::None,
+        }
+    }
+
+    fn rep_bool(state: bool) -> char {
+        WallState::from_bool(state).rep()
+    }
+}
+
+#[derive(PartialEq, Copy, Clone)]
+enum FloorState {
+    None,
+    Bott,
+}
+
+impl FloorState {
+    fn rep(&self) -> char {
+        use FloorState::*;
+        match self {
+            None => '-',
+            Bott => '#',
+        }
+    }
+
+    fn from_bool(state: bool) -> FloorState {
+        match state {
+            true => FloorState::Bott,
+            false => FloorState::None,
+        }
+    }
+
+    fn rep_bool(state: bool) -> char {
+        FloorState::from_bool(state).rep()
+    }
+}
+
+#[derive(PartialEq, Eq, Hash, Copy, Clone, Debug)]
+struct Cell {
+    state: CellState,
+    partition: usize,
+    // Could include the board cooridnate, but I don't think I want that.
+}
+
+impl Cell {
+    /// The cell's state, followed by its aquarium's label if given, `width` characters in all
+    fn rep(&self, label: Option<&str>, width: usize) -> String {
+        format!("{}{:>2$}", self.state.rep(), label.unwrap_or(""), width - 1)
+    }
+
+    fn rep_width() -> usize {
+        {
+            // Todo: Learn how to check this at compile time
+            // let null_cell = Cell {
+            //     state: CellState::Empty,
+            //     partition: 9,
+            // };
+            // assert_eq!(
+            //     null_cell.rep(None).chars().count(),
+            //     null_cell.rep(Some("9")).chars().count(),
+            // );
+        }
+        3
+    }
+}
+
+/// A line between two rows or two columns, along which `Board::split_partition` cuts
+#[derive(Copy, Clone, Debug)]
+enum Cut {
+    /// Between row iy and row iy + 1
+    Below(usize),
+    /// Between column ix and column ix + 1
+    Right(usize),
+}
+
+/// Number the cells the walls split the board into, in reading order, and count them.
+/// `walls` holds whether a wall stands to the right of each cell but the last of each row, row
+/// by row, then whether there is a floor under each cell of each row but the last.
+pub fn regions(width: usize, height: usize, walls: &[bool]) -> (Vec<usize>, usize) {
+    let floors = &walls[height * (width - 1)..];
+    let mut region = vec![usize::MAX; width * height];
+    let mut n_regions = 0;
+    for start in 0..width * height {
+        if region[start] != usize::MAX {
+            continue;
+        }
+        region[start] = n_regions;
+        let mut stack = vec![start];
+        while let Some(i) = stack.pop() {
+            let (ix, iy) = (i % width, i / width);
+            let mut open = Vec::with_capacity(4);
+            if ix > 0 && !walls[iy * (width - 1) + ix - 1] {
+                open.push(i - 1);
+            }
+            if ix + 1 < width && !walls[iy * (width - 1) + ix] {
+                open.push(i + 1);
+            }
+            if iy > 0 && !floors[(iy - 1) * width + ix] {
+                open.push(i - width);
+            }
+            if iy + 1 < height && !floors[iy * width + ix] {
+                open.push(i + width);
+            }
+            for next in open {
+                if region[next] == usize::MAX {
+                    region[next] = n_regions;
+                    stack.push(next);
+                }
+            }
+        }
+        n_regions += 1;
+    }
+    (region, n_regions)
+}
+
+/// What a puzzle's hints count
+#[derive(PartialEq, Eq, Copy, Clone, Debug, Default)]
+enum HintMode {
+    /// Each line holds exactly as many flooded cells as its hint
+    #[default]
+    Exact,
+    /// Each line holds at least as many flooded cells as its hint. Only the rules that flood
+    /// cells a line can't do without, R2 and R4, still hold; the others rely on the count being
+    /// exact.
+    AtLeast,
+}
+
+impl HintMode {
+    fn from_name(name: &str) -> Option<HintMode> {
+        match name {
+            "exact" => Some(HintMode::Exact),
+            "at-least" => Some(HintMode::AtLeast),
+            _ => None,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            HintMode::Exact => "exact",
+            HintMode::AtLeast => "at-least",
+        }
+    }
+}
+
+/// The deduction rules applied by `Board::solve`
+#[derive(PartialEq, Eq, Hash, Copy, Clone, Debug)]
+enum Rule {
+    /// Row: an aquarium with more cells in the row than the row has left can't reach it
+    R1,
+    /// Row: an aquarium is needed to reach the row hint
+    R2,
+    /// Column: an aquarium has more empty cells in the column than the column has left
+    R3,
+    /// Column: an aquarium is needed to reach the column hint
+    R4,
+    /// Row: an aquarium is in every, or no, combination of undecided aquariums that reaches the row hint
+    R5,
+    /// Rows and columns: an aquarium's level is in every, or no, combination of levels that makes
+    /// up the hints of a band of neighbouring rows or columns
+    R6,
+}
+
+impl Rule {
+    const ALL: [Rule; 6] = [Rule::R1, Rule::R2, Rule::R3, Rule::R4, Rule::R5, Rule::R6];
+
+    /// The rule named `r1` to `r6`, in either case
+    fn from_name(name: &str) -> Option<Rule> {
+        Rule::ALL
+            .iter()
+            .copied()
+            .find(|rule| format!("{:?}", rule).eq_ignore_ascii_case(name))
+    }
+
+    /// The state the rule assigns to the cells it touches. Empty if it can go either way.
+    fn state(&self) -> CellState {
+        use Rule::*;
+        match self {
+            R1 | R3 => CellState::Invalid,
+            R2 | R4 => CellState::Flooded,
+            R5 | R6 => CellState::Empty,
+        }
+    }
+}
+
+/// The name a human solver would give to a deduction
+#[derive(PartialEq, Eq, Hash, Copy, Clone, Debug)]
+enum Technique {
+    FullRow,
+    Overflow,
+    CrossHatchCount,
+    ForcedLevel,
+    SubsetSum,
+    BandCount,
+}
+
+impl Technique {
+    fn name(&self) -> &'static str {
+        use Technique::*;
+        match self {
+            FullRow => "Full row",
+            Overflow => "Overflow",
+            CrossHatchCount => "Cross-hatch count",
+            ForcedLevel => "Forced level",
+            SubsetSum => "Subset sum",
+            BandCount => "Band count",
+        }
+    }
+
+    fn description(&self) -> &'static str {
+        use Technique::*;
+        match self {
+            FullRow => {
+                "The row needs every one of its undecided cells, \
+                so every aquarium in the row is filled at least this high."
+            }
+            Overflow => {
+                "Filling the aquarium this high would put more water in the line than its hint allows, \
+                so the water stays below this cell."
+            }
+            CrossHatchCount => {
+                "Even with every other undecided cell in the row flooded the hint isn't reached, \
+                so this aquarium must be filled this high."
+            }
+            ForcedLevel => {
+                "The rest of the column can't reach the hint on its own, \
+                which forces the water in this aquarium up to at least this cell."
+            }
+            SubsetSum => {
+                "An aquarium fills all of its cells in a row or none of them. \
+                Of the combinations of undecided aquariums that add up to the row hint, \
+                this aquarium is in all of them or in none of them."
+            }
+            BandCount => {
+                "The hints of a band of rows or columns add up to the water the aquariums hold there. \
+                Of the levels the aquariums can take, only some make up the band's total, \
+                and every one of them fills this aquarium to the same side of this cell."
+            }
+        }
+    }
+}
+
+/// A single application of a rule to the cell at (ix, iy)
+#[derive(Clone, Debug)]
+pub struct Deduction {
+    /// The solver pass the deduction was made in, starting at 1
+    iteration: usize,
+    rule: Rule,
+    technique: Technique,
+    ix: usize,
+    iy: usize,
+    /// The state given to the cell
+    state: CellState,
+    /// Human readable justification
+    reason: String,
+}
+
+impl Deduction {
+    fn verb(&self) -> &'static str {
+        match self.state {
+            CellState::Flooded => "Flood",
+            _ => "Invalidate",
+        }
+    }
+
+    /// Apply the deduction to a board
+    fn apply(&self, board: &mut Board) {
+        match self.state {
+            CellState::Flooded => board.flood(self.ix, self.iy),
+            _ => board.invalidate(self.ix, self.iy),
+        }
+    }
+}
+
+impl fmt::Display for Deduction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{:?}: {} {}, {}",
+            self.rule,
+            self.verb(),
+            self.ix,
+            self.iy
+        )
+    }
+}
+
+/// Buffers a rule reuses from one line to the next
+struct LineScratch {
+    segments: Vec<(usize, isize)>,
+    first_ix: Vec<Option<usize>>,
+}
+
+impl LineScratch {
+    fn new(n_partitions: usize) -> LineScratch {
+        LineScratch {
+            segments: Vec::new(),
+            first_ix: Vec::with_capacity(n_partitions),
+        }
+    }
+}
+
+/// The most lines the band rule adds up at once. The number of bands, and the sums each one
+/// needs, grow with this, and the rule would otherwise swamp the search on a large board.
+const MAX_BAND: usize = 4;
+
+/// A run of neighbouring lines whose hints the band rule adds up
+#[derive(Copy, Clone, Debug)]
+enum Band {
+    /// The rows from top to bottom
+    Rows(usize, usize),
+    /// The columns from left to right
+    Cols(usize, usize),
+}
+
+impl Band {
+    /// The cells in the band of an aquarium's row, given the columns they're in
+    fn cells_in(&self, iy: usize, columns: &[usize]) -> usize {
+        match *self {
+            Band::Rows(top, bottom) if (top..=bottom).contains(&iy) => columns.len(),
+            Band::Rows(..) => 0,
+            Band::Cols(left, right) => columns
+                .iter()
+                .filter(|ix| (left..=right).contains(ix))
+                .count(),
+        }
+    }
+}
+
+impl fmt::Display for Band {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Band::Rows(top, bottom) => write!(f, "Rows {} to {}", top, bottom),
+            Band::Cols(left, right) if left == right => write!(f, "Column {}", left),
+            Band::Cols(left, right) => write!(f, "Columns {} to {}", left, right),
+        }
+    }
+}
+
+/// An aquarium's rows, for the band rule
+struct AquariumRows {
+    /// Its rows from the bottom up: (iy, the columns of its cells in the row)
+    rows: Vec<(usize, Vec<usize>)>,
+    /// The fewest and most of its rows that can be under water, or None if no level fits
+    range: Option<(usize, usize)>,
+}
+
+/// The cells of one aquarium in one line that are undecided and invalid
+#[derive(Default, Copy, Clone)]
+struct StateCounts {
+    empty: isize,
+    invalid: isize,
+}
+
+#[derive(Clone)]
+pub struct Board {
+    // Visual properties of the board
+    width: usize,
+    height: usize,
+    // width x height
+    cells: Vec<Cell>,
+    // height
+    row_hints: Vec<isize>,
+    // width
+    col_hints: Vec<isize>,
+    // Whether the hints are exact counts or only lower bounds
+    hint_mode: HintMode,
+    // The flooded cells of each row and of each column as bits.
+    // Kept in step with `cells` by `set_cell_at`, and rebuilt by `index_states`
+    // after writing to `cells` directly.
+    flooded_rows: bits::BitLines,
+    flooded_cols: bits::BitLines,
+    // Counts of the undecided and invalid cells, kept in step the same way.
+    // For each row: undecided cells
+    row_empty: Vec<isize>,
+    // For each col: undecided cells
+    col_empty: Vec<isize>,
+    // For each col and partition: the undecided and invalid cells, in a single array at
+    // [ix * n_partitions + partition]
+    col_partition_counts: Vec<StateCounts>,
+    // Structure of the partitions, which doesn't change while solving.
+    // Rebuilt by `index_partitions` whenever the partitions change.
+    //
+    // Partitions are numbered 0..n_partitions
+    n_partitions: usize,
+    // [partition] the label the puzzle file gave it, for display.
+    // Empty when the partitions have only their numbers.
+    partition_labels: Vec<String>,
+    // For each row: [partition] size
+    row_partitions: Vec<Vec<isize>>,
+    // For each col: [partition] iy_list
+    col_partitions: Vec<Vec<Vec<usize>>>,
+    // The most passes `solve` makes before giving up on the rules settling. By default one more
+    // than the number of cells, since every pass that changes the board decides at least one cell
+    // unless a rule is undoing another's work.
+    max_iterations: Option<usize>,
+    // Rules `solve` leaves out, to see what the others manage without them
+    disabled_rules: Vec<Rule>,
+}
+
+impl Board {
+    fn cell_at(&self, ix: usize, iy: usize) -> Cell {
+        assert!(ix < self.width && iy < self.height);
+        let row_offset = iy * self.width;
+        self.cells[row_offset + ix]
+    }
+
+    pub fn cell_state_at(&self, ix: usize, iy: usize) -> CellState {
+        assert!(ix < self.width && iy < self.height);
+        let row_offset = iy * self.width;
+        self.cells[row_offset + ix].state
+    }
+
+    fn set_cell_at(&mut self, ix: usize, iy: usize, state: CellState) {
+        assert!(ix < self.width && iy < self.height);
+        let row_offset = iy * self.width;
+        let cell = self.cells[row_offset + ix];
+        if cell.state == state {
+            return;
+        }
+        self.cells[row_offset + ix].state = state;
+        self.count_state(ix, iy, cell.partition, cell.state, -1);
+        self.count_state(ix, iy, cell.partition, state, 1);
+        let flooded = state == CellState::Flooded;
+        self.flooded_rows.set(iy, ix, flooded);
+        self.flooded_cols.set(ix, iy, flooded);
+    }
+
+    /// Add `by` to the counts a cell at (ix, iy) in `partition` with `state` takes part in
+    fn count_state(&mut self, ix: usize, iy: usize, partition: usize, state: CellState, by: isize) {
+        match state {
+            CellState::Empty => {
+                self.row_empty[iy] += by;
+                self.col_empty[ix] += by;
+                self.col_partition_counts[ix * self.n_partitions + partition].empty += by;
+            }
+            CellState::Invalid => {
+                self.col_partition_counts[ix * self.n_partitions + partition].invalid += by
+            }
+            CellState::Flooded => {}
+        }
+    }
+
+    pub fn partition_at(&self, ix: usize, iy: usize) -> usize {
+        assert!(ix < self.width && iy < self.height);
+        let row_offset = iy * self.width;
+        self.cells[row_offset + ix].partition
+    }
+
+    //---
+    //
+    fn wall_at(&self, ix: usize, iy: usize) -> bool {
+        assert!(ix + 1 < self.width && iy < self.height);
+        self.partition_at(ix, iy) != self.partition_at(ix + 1, iy)
+    }
+    //
+    fn floor_at(&self, ix: usize, iy: usize) -> bool {
+        assert!(ix < self.width && iy + 1 < self.height);
+        self.partition_at(ix, iy) != self.partition_at(ix, iy + 1)
+    }
+
+    // TODO
+    fn make(width: usize, height: usize) -> Board {
+        let mut board = Board {
+            width,
+            height,
+            cells: vec![
+                Cell {
+                    state: CellState::Empty,
+                    partition: 0
+                };
+                width * height
+            ],
+            row_hints: vec![0; height],
+            col_hints: vec![0; width],
+            hint_mode: HintMode::Exact,
+            flooded_rows: bits::BitLines::new(height, width),
+            flooded_cols: bits::BitLines::new(width, height),
+            row_empty: Vec::new(),
+            col_empty: Vec::new(),
+            col_partition_counts: Vec::new(),
+            n_partitions: 0,
+            partition_labels: Vec::new(),
+            row_partitions: Vec::new(),
+            col_partitions: Vec::new(),
+            max_iterations: None,
+            disabled_rules: Vec::new(),
+        };
+        board.index_partitions();
+        board
+    }
+
+    /// A puzzle with `partitions` giving each cell's aquarium in reading order, and exact hints.
+    /// Any numbers will do for the aquariums; cells with the same number are the same aquarium.
+    pub fn new(
+        width: usize,
+        height: usize,
+        partitions: &[usize],
+        row_hints: &[usize],
+        col_hints: &[usize],
+    ) -> Result<Board, String> {
+        if width == 0 || height == 0 {
+            return Err("The board is empty".to_string());
+        }
+        if partitions.len() != width * height {
+            return Err(format!(
+                "{} aquarium numbers for a {}x{} board",
+                partitions.len(),
+                width,
+                height
+            ));
+        }
+        if row_hints.len() != height || col_hints.len() != width {
+            return Err(format!(
+                "{} row and {} column hints for a {}x{} board",
+                row_hints.len(),
+                col_hints.len(),
+                width,
+                height
+            ));
+        }
+        if let Some(iy) = row_hints.iter().position(|&hint| hint > width) {
+            return Err(format!("Row {} hint is more than the width", iy));
+        }
+        if let Some(ix) = col_hints.iter().position(|&hint| hint > height) {
+            return Err(format!("Column {} hint is more than the height", ix));
+        }
+        if row_hints.iter().sum::<usize>() != col_hints.iter().sum::<usize>() {
+            return Err("Row and column hints don't add up to the same total".to_string());
+        }
+
+        let mut board = Board::make(width, height);
+        for (cell, &partition) in board.cells.iter_mut().zip(partitions) {
+            cell.partition = partition;
+        }
+        let hint = |hint: &usize| isize::try_from(*hint).unwrap();
+        board.row_hints = row_hints.iter().map(hint).collect();
+        board.col_hints = col_hints.iter().map(hint).collect();
+        board.index_partitions();
+        Ok(board)
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    #[allow(clippy::zero_prefixed_literal)]
+    fn make_b0() -> Board {
+        // 6x6 Easy ID: 3,095,209 https://www.puzzle-aquarium.com/specfic.php
+
+        let width = 6;
+        let height = 6;
+        let count = width * height;
+        let partitions = vec![
+            00, 00, 00, 00, 01, 01, //
+            00, 00, 02, 02, 01, 01, //
+            03, 00, 03, 02, 04, 05, //
+            03, 03, 03, 02, 04, 05, //
+            03, 03, 03, 03, 03, 05, //
+            03, 03, 05, 05, 05, 05,
+        ];
+
+        let cells: Vec<_> = partitions
+            .iter()
+            .map(|&partition| Cell {
+                state: CellState::Empty,
+                partition,
+            })
+            .collect();
+
+        let mut board = Board {
+            width,
+            height,
+            cells,
+            row_hints: vec![2, 4, 3, 2, 1, 4],
+            col_hints: vec![1, 2, 1, 3, 5, 4],
+            hint_mode: HintMode::Exact,
+            flooded_rows: bits::BitLines::new(height, width),
+            flooded_cols: bits::BitLines::new(width, height),
+            row_empty: Vec::new(),
+            col_empty: Vec::new(),
+            col_partition_counts: Vec::new(),
+            n_partitions: 0,
+            partition_labels: Vec::new(),
+            row_partitions: Vec::new(),
+            col_partitions: Vec::new(),
+            max_iterations: None,
+            disabled_rules: Vec::new(),
+        };
+        board.index_partitions();
+
+        board
+    }
+
+    fn make_b0_solved() -> Board {
+        let mut board = Board::make_b0();
+        use CellState::*;
+
+        let states = vec![
+            Invalid, Invalid, Invalid, Invalid, Flooded, Flooded, //
+            Flooded, Flooded, Invalid, Invalid, Flooded, Flooded, //
+            Invalid, Flooded, Invalid, Flooded, Flooded, Invalid, //
+            Invalid, Invalid, Invalid, Flooded, Flooded, Invalid, //
+            Invalid, Invalid, Invalid, Invalid, Invalid, Flooded, //
+            Invalid, Invalid, Flooded, Flooded, Flooded, Flooded,
+        ];
+
+        for (i, state) in states.into_iter().enumerate() {
+            board.set_cell_at(i % board.width, i / board.width, state);
+        }
+
+        board
+    }
+
+    /// Rebuild the flooded bits and the counts of undecided and invalid cells after writing to
+    /// `cells` directly
+    fn index_states(&mut self) {
+        self.flooded_rows.clear();
+        self.flooded_cols.clear();
+        self.row_empty = vec![0; self.height];
+        self.col_empty = vec![0; self.width];
+        self.col_partition_counts = vec![StateCounts::default(); self.width * self.n_partitions];
+        for iy in 0..self.height {
+            for ix in 0..self.width {
+                let cell = self.cell_at(ix, iy);
+                self.count_state(ix, iy, cell.partition, cell.state, 1);
+                let flooded = cell.state == CellState::Flooded;
+                self.flooded_rows.set(iy, ix, flooded);
+                self.flooded_cols.set(ix, iy, flooded);
+            }
+        }
+    }
+
+    /// Renumber the partitions 0..n, keeping their order,
+    /// and rebuild the row and column partition structure and the cell counts after the
+    /// partitions change
+    fn index_partitions(&mut self) {
+        let mut ids: Vec<_> = self.cells.iter().map(|cell| cell.partition).collect();
+        ids.sort_unstable();
+        ids.dedup();
+        for cell in self.cells.iter_mut() {
+            cell.partition = ids.binary_search(&cell.partition).unwrap();
+        }
+        self.n_partitions = ids.len();
+        if !self.partition_labels.is_empty() {
+            self.partition_labels = ids.iter().map(|&id| self.partition_label(id)).collect();
+        }
+
+        // For each row: [partition] size
+        self.row_partitions = (0..self.height)
+            .map(|iy| {
+                let mut map_sizes = vec![0; self.n_partitions];
+                for ix in 0..self.width {
+                    map_sizes[self.partition_at(ix, iy)] += 1;
+                }
+                map_sizes
+            })
+            .collect();
+
+        // For each col: [partition] iy_list
+        self.col_partitions = (0..self.width)
+            .map(|ix| {
+                let mut map_idx = vec![Vec::new(); self.n_partitions];
+                for iy in 0..self.height {
+                    map_idx[self.partition_at(ix, iy)].push(iy);
+                }
+                map_idx
+            })
+            .collect();
+        self.index_states();
+    }
+
+    /// How to show a partition: its label from the puzzle file, or else its number
+    fn partition_label(&self, partition: usize) -> String {
+        match self.partition_labels.get(partition) {
+            Some(label) => label.clone(),
+            None => partition.to_string(),
+        }
+    }
+
+    /// The partition ids on the board, in ascending order
+    fn partitions(&self) -> Vec<usize> {
+        (0..self.n_partitions).collect()
+    }
+
+    /// The water level of an aquarium: the topmost flooded row.
+    /// None if the aquarium is left unfilled or any of its cells are still undetermined.
+    fn water_level(&self, partition: usize) -> Option<usize> {
+        let mut level = None;
+        for iy in (0..self.height).rev() {
+            for ix in 0..self.width {
+                let cell = self.cell_at(ix, iy);
+                if cell.partition != partition {
+                    continue;
+                }
+                match cell.state {
+                    CellState::Empty => return None,
+                    CellState::Flooded => level = Some(iy),
+                    CellState::Invalid => {}
+                }
+            }
+        }
+        level
+    }
+
+    /// The water level of every aquarium, ordered by partition id
+    fn levels(&self) -> Vec<(usize, Option<usize>)> {
+        self.partitions()
+            .into_iter()
+            .map(|partition| (partition, self.water_level(partition)))
+            .collect()
+    }
+
+    /// The least and most rows of an aquarium, counting up from its bottom row, that can be under
+    /// water given the cells decided so far. None if no depth fits them.
+    fn depth_range(&self, partition: usize) -> Option<(usize, usize)> {
+        let mut rows = Vec::new();
+        let mut least = 0;
+        let mut most = usize::MAX;
+        // Bottom row first, so a cell's depth is the number of the aquarium's rows seen so far
+        for iy in (0..self.height).rev() {
+            for ix in 0..self.width {
+                let cell = self.cell_at(ix, iy);
+                if cell.partition != partition {
+                    continue;
+                }
+                if rows.last() != Some(&iy) {
+                    rows.push(iy);
+                }
+                match cell.state {
+                    CellState::Empty => {}
+                    CellState::Flooded => least = least.max(rows.len()),
+                    CellState::Invalid => most = most.min(rows.len() - 1),
+                }
+            }
+        }
+        let most = most.min(rows.len());
+        (least <= most).then_some((least, most))
+    }
+
+    /// Flood every cell as deep as a flooded cell of its aquarium and dry every cell as high as a
+    /// dry one, since the water in an aquarium lies level. The rules rely on an aquarium's cells
+    /// in a row being decided together, which cells given by hand needn't be.
+    /// Fails, naming the aquarium, if no level fits the cells already decided.
+    fn settle_levels(&mut self) -> Result<(), String> {
+        for partition in self.partitions() {
+            let (least, most) = self.depth_range(partition).ok_or_else(|| {
+                format!(
+                    "No water level fits the cells of aquarium {}",
+                    self.partition_label(partition)
+                )
+            })?;
+            let mut depth = 0;
+            for iy in (0..self.height).rev() {
+                let cells: Vec<_> = (0..self.width)
+                    .filter(|&ix| self.partition_at(ix, iy) == partition)
+                    .collect();
+                if cells.is_empty() {
+                    continue;
+                }
+                depth += 1;
+                let state = if depth <= least {
+                    CellState::Flooded
+                } else if depth > most {
+                    CellState::Invalid
+                } else {
+                    continue;
+                };
+                for ix in cells {
+                    self.set_cell_at(ix, iy, state);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// How many rows of each aquarium are under water, counting up from its bottom row, in
+    /// partition order. None unless every cell is decided and lies level in its aquarium.
+    fn depths(&self) -> Option<Vec<usize>> {
+        if self.cells.iter().any(|cell| cell.state == CellState::Empty) {
+            return None;
+        }
+        self.partitions()
+            .into_iter()
+            .map(|partition| match self.depth_range(partition) {
+                Some((least, most)) if least == most => Some(least),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Fill an aquarium `depth` of its rows deep, counting up from its bottom row, as
+    /// `set_water_level` does. A depth of 0 leaves it unfilled.
+    fn set_depth(&mut self, partition: usize, depth: usize) -> Result<(), String> {
+        let rows: Vec<_> = (0..self.height)
+            .filter(|&iy| (0..self.width).any(|ix| self.partition_at(ix, iy) == partition))
+            .collect();
+        let level = match depth {
+            0 => None,
+            _ => Some(*rows.iter().rev().nth(depth - 1).ok_or_else(|| {
+                format!(
+                    "Aquarium {} is only {} rows deep",
+                    self.partition_label(partition),
+                    rows.len()
+                )
+            })?),
+        };
+        self.set_water_level(partition, level)
+    }
+
+    /// Fill an aquarium to the given level: every cell in the aquarium at or below row `level`
+    /// is flooded and every cell above it is invalidated. A level of None leaves the aquarium unfilled.
+    ///
+    /// The board is left untouched if the aquarium doesn't reach the level row
+    /// or if any of its cells are already decided the other way.
+    fn set_water_level(&mut self, partition: usize, level: Option<usize>) -> Result<(), String> {
+        let cells: Vec<_> = (0..self.height)
+            .flat_map(|iy| (0..self.width).map(move |ix| (ix, iy)))
+            .filter(|&(ix, iy)| self.partition_at(ix, iy) == partition)
+            .collect();
+        if cells.is_empty() {
+            return Err(format!(
+                "There is no aquarium {}",
+                self.partition_label(partition)
+            ));
+        }
+        if let Some(level) = level {
+            if !cells.iter().any(|&(_, iy)| iy == level) {
+                return Err(format!(
+                    "Aquarium {} has no cells in row {}",
+                    self.partition_label(partition),
+                    level
+                ));
+            }
+        }
+
+        let target = |iy: usize| match level {
+            Some(level) if iy >= level => CellState::Flooded,
+            _ => CellState::Invalid,
+        };
+        for &(ix, iy) in &cells {
+            let state = self.cell_state_at(ix, iy);
+            if state != CellState::Empty && state != target(iy) {
+                return Err(format!(
+                    "Cell ({}, {}) of aquarium {} is already {:?}",
+                    ix,
+                    iy,
+                    self.partition_label(partition),
+                    state
+                ));
+            }
+        }
+
+        for &(ix, iy) in &cells {
+            self.set_cell_at(ix, iy, target(iy));
+        }
+        Ok(())
+    }
+
+    /// Insert an undecided row before row `at`, or after the last row if `at` is the height.
+    /// The new row takes the aquariums of the row above it, or of the row below for a new top
+    /// row, so they stretch over it. Its hint is 0; the other hints are kept.
+    fn insert_row(&mut self, at: usize) {
+        assert!(at <= self.height);
+        let copy = at.saturating_sub(1);
+        let row: Vec<_> = (0..self.width)
+            .map(|ix| Cell {
+                state: CellState::Empty,
+                partition: self.partition_at(ix, copy),
+            })
+            .collect();
+        let offset = at * self.width;
+        self.cells.splice(offset..offset, row);
+        self.row_hints.insert(at, 0);
+        self.height += 1;
+        self.reshape();
+    }
+
+    /// Remove row `at` and its hint. An aquarium lying only in the row goes with it, and one the
+    /// row joined together becomes an aquarium for each piece, as in `separate_pieces`.
+    fn remove_row(&mut self, at: usize) {
+        assert!(at < self.height && self.height > 1);
+        let offset = at * self.width;
+        self.cells.drain(offset..offset + self.width);
+        self.row_hints.remove(at);
+        self.height -= 1;
+        self.separate_pieces();
+        self.reshape();
+    }
+
+    /// Insert an undecided column before column `at`, or after the last column if `at` is the
+    /// width. The new column takes the aquariums of the column to its left, or of the column to
+    /// its right for a new first column. Its hint is 0; the other hints are kept.
+    fn insert_col(&mut self, at: usize) {
+        assert!(at <= self.width);
+        let copy = at.saturating_sub(1);
+        let mut cells = Vec::with_capacity((self.width + 1) * self.height);
+        for row in self.cells.chunks(self.width) {
+            cells.extend_from_slice(&row[..at]);
+            cells.push(Cell {
+                state: CellState::Empty,
+                partition: row[copy].partition,
+            });
+            cells.extend_from_slice(&row[at..]);
+        }
+        self.cells = cells;
+        self.col_hints.insert(at, 0);
+        self.width += 1;
+        self.reshape();
+    }
+
+    /// Remove column `at` and its hint, like `remove_row`
+    fn remove_col(&mut self, at: usize) {
+        assert!(at < self.width && self.width > 1);
+        let width = self.width;
+        self.cells = self
+            .cells
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| i % width != at)
+            .map(|(_, &cell)| cell)
+            .collect();
+        self.col_hints.remove(at);
+        self.width -= 1;
+        self.separate_pieces();
+        self.reshape();
+    }
+
+    /// Give every piece of an aquarium that no longer hangs together its own number. The first
+    /// piece in reading order keeps the aquarium's number and label; the others are numbered
+    /// after the rest, with fresh labels.
+    fn separate_pieces(&mut self) {
+        let mut walls = Vec::new();
+        for iy in 0..self.height {
+            walls.extend((0..self.width - 1).map(|ix| self.wall_at(ix, iy)));
+        }
+        for iy in 0..self.height - 1 {
+            walls.extend((0..self.width).map(|ix| self.floor_at(ix, iy)));
+        }
+        let (regions, n_regions) = regions(self.width, self.height, &walls);
+
+        let mut numbers = vec![None; n_regions];
+        let mut kept = vec![false; self.n_partitions];
+        let mut next = self.n_partitions;
+        for (cell, &region) in self.cells.iter_mut().zip(&regions) {
+            let number = *numbers[region].get_or_insert_with(|| {
+                if !kept[cell.partition] {
+                    kept[cell.partition] = true;
+                    return cell.partition;
+                }
+                next += 1;
+                next - 1
+            });
+            cell.partition = number;
+        }
+        for new in self.n_partitions..next {
+            self.push_fresh_label(new);
+        }
+    }
+
+    /// Label a new aquarium numbered `new` after its number, or the first number after it no
+    /// other aquarium is labelled with. Nothing to do on a board without labels.
+    fn push_fresh_label(&mut self, new: usize) {
+        if !self.partition_labels.is_empty() {
+            let label = (new..)
+                .map(|n| n.to_string())
+                .find(|label| !self.partition_labels.contains(label))
+                .unwrap();
+            self.partition_labels.push(label);
+        }
+    }
+
+    /// Join aquarium `b` onto aquarium `a`, which must touch it. The merged aquarium keeps `a`'s
+    /// label, and the aquariums after `b` move down a number.
+    fn merge_partitions(&mut self, a: usize, b: usize) -> Result<(), String> {
+        let pair = |p: usize, q: usize| (p == a && q == b) || (p == b && q == a);
+        let touch = (0..self.height).any(|iy| {
+            (0..self.width).any(|ix| {
+                let here = self.partition_at(ix, iy);
+                (ix + 1 < self.width && pair(here, self.partition_at(ix + 1, iy)))
+                    || (iy + 1 < self.height && pair(here, self.partition_at(ix, iy + 1)))
+            })
+        });
+        if a == b || !touch {
+            return Err(format!(
+                "Aquariums {} and {} don't touch",
+                self.partition_label(a),
+                self.partition_label(b)
+            ));
+        }
+        for cell in self.cells.iter_mut().filter(|cell| cell.partition == b) {
+            cell.partition = a;
+        }
+        self.index_partitions();
+        Ok(())
+    }
+
+    /// Split an aquarium along a line between two rows or two columns. The part above or left of
+    /// the line keeps the aquarium's number and label; the other part becomes a new aquarium,
+    /// numbered after the rest.
+    fn split_partition(&mut self, partition: usize, cut: Cut) -> Result<(), String> {
+        let (ix_max, iy_max) = match cut {
+            Cut::Below(iy) => (self.width, iy + 1),
+            Cut::Right(ix) => (ix + 1, self.height),
+        };
+        let cells: Vec<_> = (0..self.height)
+            .flat_map(|iy| (0..self.width).map(move |ix| (ix, iy)))
+            .filter(|&(ix, iy)| self.partition_at(ix, iy) == partition)
+            .collect();
+        let (_, after): (Vec<_>, Vec<_>) = cells
+            .iter()
+            .partition(|&&(ix, iy)| ix < ix_max && iy < iy_max);
+        if after.is_empty() || after.len() == cells.len() {
+            return Err(format!(
+                "The line doesn't run through aquarium {}",
+                self.partition_label(partition)
+            ));
+        }
+        let new = self.n_partitions;
+        self.push_fresh_label(new);
+        for (ix, iy) in after {
+            self.cells[iy * self.width + ix].partition = new;
+        }
+        self.index_partitions();
+        Ok(())
+    }
+
+    /// The aquarium shown as `label`
+    fn partition_by_label(&self, label: &str) -> Option<usize> {
+        (0..self.n_partitions).find(|&partition| self.partition_label(partition) == label)
+    }
+
+    /// Rebuild everything worked out from the cells after the board changes size
+    fn reshape(&mut self) {
+        self.flooded_rows = bits::BitLines::new(self.height, self.width);
+        self.flooded_cols = bits::BitLines::new(self.width, self.height);
+        self.index_partitions();
+    }
+
+    /// The board flipped left to right
+    fn mirrored(&self) -> Board {
+        let mut board = self.clone();
+        for iy in 0..self.height {
+            for ix in 0..self.width {
+                board.cells[iy * self.width + ix] = self.cell_at(self.width - 1 - ix, iy);
+            }
+        }
+        board.col_hints.reverse();
+        board.index_partitions();
+        board
+    }
+
+    /// Reset every cell to empty, leaving the layout and hints alone
+    fn clear(&mut self) {
+        for cell in self.cells.iter_mut() {
+            cell.state = CellState::Empty;
+        }
+        self.index_states();
+    }
+
+    /// Set each cell in the same partition as the cell at (ix, iy)
+    /// and the same or lower row (iy) to be flooded
+    fn flood(&mut self, ix: usize, iy: usize) {
+        let partition = self.partition_at(ix, iy);
+        for iy in iy..self.height {
+            for ix in 0..self.width {
+                if self.partition_at(ix, iy) == partition {
+                    self.set_cell_at(ix, iy, CellState::Flooded);
+                }
+            }
+        }
+    }
+
+    /// Set each cell in the same partition as the cell at (ix, iy)
+    /// and the same or higher row (iy) to be invalid
+    fn invalidate(&mut self, ix: usize, iy: usize) {
+        let partition = self.partition_at(ix, iy);
+        for iy in 0..iy + 1 {
+            for ix in 0..self.width {
+                if self.partition_at(ix, iy) == partition {
+                    self.set_cell_at(ix, iy, CellState::Invalid);
+                }
+            }
+        }
+    }
+
+    pub fn solve(&mut self) -> SolveStats {
+        self.solve_traced(&mut Vec::new())
+    }
+
+    /// Solve as far as the rules allow, recording every deduction made into `trace`.
+    ///
+    /// Each rule looks at one line at a time, so within a pass the lines are examined in parallel
+    /// against the same board and the deductions they propose are applied afterwards, in line order.
+    /// Lines, aquariums and cells are all visited by index, so the trace is the same on every run
+    /// whatever the number of threads, and can be compared against a saved one.
+    fn solve_traced(&mut self, trace: &mut Vec<Deduction>) -> SolveStats {
+        self.solve_observed(trace, &mut ())
+    }
+
+    /// Like `solve_traced`, telling `observer` about every deduction and pass,
+    /// and stopping straight away if it asks to
+    pub fn solve_observed(
+        &mut self,
+        trace: &mut Vec<Deduction>,
+        observer: &mut dyn SolverObserver,
+    ) -> SolveStats {
+        #[cfg(feature = "std")]
+        let start = std::time::Instant::now();
+        let mut stats = SolveStats::default();
+
+        // Subset sums for each row, kept between passes
+        let mut row_feasibility: Vec<_> = (0..self.height)
+            .map(|_| feasibility::RowFeasibility::default())
+            .collect();
+
+        // println!("{:#?}", self.col_partitions);
+        // return; // DEBUG
+
+        let max_iterations = self.max_iterations.unwrap_or(self.width * self.height + 1);
+        loop {
+            if stats.iterations == max_iterations {
+                stats.stagnated = true;
+                #[cfg(feature = "std")]
+                eprintln!("{}", self.stagnation_report(trace, stats.iterations));
+                break;
+            }
+            stats.iterations += 1;
+            let pass = self.solve_pass(&mut row_feasibility, trace, &mut stats, observer);
+            let ControlFlow::Continue(updated) = pass else {
+                stats.stopped = true;
+                break;
+            };
+            observer.on_iteration_end(self, stats.iterations, updated);
+            if !updated {
+                break;
+            }
+        }
+
+        #[cfg(feature = "std")]
+        {
+            stats.elapsed = start.elapsed();
+        }
+        stats
+    }
+
+    /// What the rules were doing when they hit the iteration cap: the deductions of the last pass
+    /// and how many cells each line still needs
+    fn stagnation_report(&self, trace: &[Deduction], iterations: usize) -> String {
+        let mut report = format!(
+            "The rules were still changing the board after {} passes. The last pass:",
+            iterations
+        );
+        for deduction in trace.iter().filter(|it| it.iteration == iterations) {
+            write!(report, "\n  {}", deduction).unwrap();
+        }
+        let remaining = |counts: Vec<isize>| {
+            let counts: Vec<_> = counts.iter().map(|count| count.to_string()).collect();
+            counts.join(" ")
+        };
+        write!(
+            report,
+            "\nRow remainders: {}\nColumn remainders: {}",
+            remaining((0..self.height).map(|iy| self.row_remaining(iy)).collect()),
+            remaining((0..self.width).map(|ix| self.col_remaining(ix)).collect())
+        )
+        .unwrap();
+        report
+    }
+
+    /// One pass over all of the enabled rules. Continues with whether anything changed,
+    /// or breaks if the observer stopped the solve. Finished lines are skipped, since no rule can
+    /// change them again.
+    fn solve_pass(
+        &mut self,
+        row_feasibility: &mut [feasibility::RowFeasibility],
+        trace: &mut Vec<Deduction>,
+        stats: &mut SolveStats,
+        observer: &mut dyn SolverObserver,
+    ) -> ControlFlow<(), bool> {
+        let iteration = stats.iterations;
+        let n_partitions = self.n_partitions;
+        let exact = self.hint_mode == HintMode::Exact;
+        let mut updated = false;
+
+        // Invalidate rows:
+        // look for n_row_part > remainder => invalidate
+        if exact && self.rule_enabled(Rule::R1) {
+            let board: &Board = self;
+            let proposed: Vec<_> = (0..board.height)
+                .into_par_iter()
+                .rev()
+                .filter(|&iy| !board.row_finished(iy))
+                .map(|iy| board.overflow_row(iy, iteration))
+                .collect();
+            updated |= self.apply_proposed(proposed, trace, stats, observer)?;
+        }
+
+        // Flood rows:
+        // Look for width - n_row_part < remainder =>  flood
+        if self.rule_enabled(Rule::R2) {
+            let board: &Board = self;
+            let proposed: Vec<_> = (0..board.height)
+                .into_par_iter()
+                .filter(|&iy| !board.row_finished(iy))
+                .map(|iy| board.cross_hatch_row(iy, iteration))
+                .collect();
+            updated |= self.apply_proposed(proposed, trace, stats, observer)?;
+        }
+
+        // Subset sums:
+        // Look for aquariums in every or no combination making up the remainder
+        if exact && self.rule_enabled(Rule::R5) {
+            let board: &Board = self;
+            let proposed: Vec<_> = row_feasibility
+                .par_iter_mut()
+                .enumerate()
+                .filter(|(iy, _)| !board.row_finished(*iy))
+                .map_init(
+                    || LineScratch::new(n_partitions),
+                    |scratch, (iy, feasibility)| {
+                        board.subset_sum_row(iy, iteration, feasibility, scratch)
+                    },
+                )
+                .collect();
+            updated |= self.apply_proposed(proposed, trace, stats, observer)?;
+        }
+
+        // Cols:
+        if self.rule_enabled(Rule::R3) || self.rule_enabled(Rule::R4) {
+            let board: &Board = self;
+            let proposed: Vec<_> = (0..board.width)
+                .into_par_iter()
+                .filter(|&ix| !board.col_finished(ix))
+                .map(|ix| board.col_deductions(ix, iteration))
+                .collect();
+            updated |= self.apply_proposed(proposed, trace, stats, observer)?;
+        }
+
+        // Bands:
+        // Look for levels that can't make up the hints of neighbouring lines together. These are
+        // the costliest to find, so only once the single lines have nothing left.
+        if exact && !updated && self.rule_enabled(Rule::R6) {
+            let board: &Board = self;
+            let aquariums = board.aquarium_rows();
+            let proposed: Vec<_> = board
+                .bands()
+                .into_par_iter()
+                .map(|band| board.band_count(&aquariums, band, iteration))
+                .collect();
+            updated |= self.apply_proposed(proposed, trace, stats, observer)?;
+        }
+
+        ControlFlow::Continue(updated)
+    }
+
+    /// Every deduction the rules can make from the board as it stands, without applying any, in
+    /// the order a pass of `solve` would meet them. Several can decide the same cells.
+    fn available_deductions(&self) -> Vec<Deduction> {
+        let exact = self.hint_mode == HintMode::Exact;
+        let mut scratch = LineScratch::new(self.n_partitions);
+        let mut deductions = Vec::new();
+        let rows = || (0..self.height).filter(|&iy| !self.row_finished(iy));
+        if exact {
+            for iy in rows().rev() {
+                deductions.extend(self.overflow_row(iy, 1));
+            }
+        }
+        for iy in rows() {
+            deductions.extend(self.cross_hatch_row(iy, 1));
+        }
+        if exact {
+            for iy in rows() {
+                let mut feasibility = feasibility::RowFeasibility::default();
+                deductions.extend(self.subset_sum_row(iy, 1, &mut feasibility, &mut scratch));
+            }
+        }
+        for ix in (0..self.width).filter(|&ix| !self.col_finished(ix)) {
+            deductions.extend(self.col_deductions(ix, 1));
+        }
+        if exact {
+            let aquariums = self.aquarium_rows();
+            for band in self.bands() {
+                deductions.extend(self.band_count(&aquariums, band, 1));
+            }
+        }
+        deductions.retain(|deduction| self.rule_enabled(deduction.rule));
+        deductions
+    }
+
+    /// Apply the deductions proposed for each line, in order.
+    /// A deduction whose cell an earlier one already decided is dropped.
+    /// Continues with whether anything was applied, or breaks if the observer stopped the solve.
+    fn apply_proposed(
+        &mut self,
+        proposed: Vec<Vec<Deduction>>,
+        trace: &mut Vec<Deduction>,
+        stats: &mut SolveStats,
+        observer: &mut dyn SolverObserver,
+    ) -> ControlFlow<(), bool> {
+        let mut updated = false;
+        for deduction in proposed.into_iter().flatten() {
+            if self.cell_state_at(deduction.ix, deduction.iy) != CellState::Empty {
+                continue;
+            }
+            deduction.apply(self);
+            updated = true;
+            stats.deductions += 1;
+            let keep_going = observer.on_deduction(self, &deduction);
+            trace.push(deduction);
+            if !keep_going {
+                return ControlFlow::Break(());
+            }
+        }
+        ControlFlow::Continue(updated)
+    }
+
+    /// R1 for row iy: invalidate aquariums too big for what the row still needs
+    fn overflow_row(&self, iy: usize, iteration: usize) -> Vec<Deduction> {
+        let mut deductions = Vec::new();
+        let map_sizes = &self.row_partitions[iy]; // partitan : size
+
+        // println!("{} counts: {:?}", iy, map_sizes);
+
+        let remainder = self.row_remaining(iy);
+        // For each partition in the row
+        for ix in 0..self.width {
+            let cell_ix = self.cell_at(ix, iy);
+            if cell_ix.state != CellState::Empty {
+                continue;
+            };
+
+            // !!!
+            if map_sizes[cell_ix.partition] > remainder {
+                deductions.push(Deduction {
+                    iteration,
+                    rule: Rule::R1,
+                    state: Rule::R1.state(),
+                    technique: Technique::Overflow,
+                    ix,
+                    iy,
+                    reason: format!(
+                        "Row {} needs {} more flooded cells, but aquarium {} has {} cells in the row",
+                        iy,
+                        remainder,
+                        self.partition_label(cell_ix.partition),
+                        map_sizes[cell_ix.partition]
+                    ),
+                });
+            }
+        }
+        deductions
+    }
+
+    /// R2 for row iy: flood aquariums the row can't do without
+    fn cross_hatch_row(&self, iy: usize, iteration: usize) -> Vec<Deduction> {
+        let mut deductions = Vec::new();
+        let map_sizes = &self.row_partitions[iy]; // partitan : size
+
+        // The number of undecided cells in the row
+        let empty_count = self.row_empty[iy];
+
+        let remainder = self.row_remaining(iy);
+
+        for ix in 0..self.width {
+            let cell_ix = self.cell_at(ix, iy);
+            if cell_ix.state != CellState::Empty {
+                continue;
+            };
+            // !!!
+
+            // If it is imposable to meet the hint without this partition
+            if empty_count - map_sizes[cell_ix.partition] < remainder {
+                deductions.push(Deduction {
+                    iteration,
+                    rule: Rule::R2,
+                    state: Rule::R2.state(),
+                    technique: if empty_count == remainder {
+                        Technique::FullRow
+                    } else {
+                        Technique::CrossHatchCount
+                    },
+                    ix,
+                    iy,
+                    reason: format!(
+                        "Row {} needs {} more flooded cells, but only {} undecided cells lie outside aquarium {}",
+                        iy,
+                        remainder,
+                        empty_count - map_sizes[cell_ix.partition],
+                        self.partition_label(cell_ix.partition)
+                    ),
+                });
+            }
+        }
+        deductions
+    }
+
+    /// R5 for row iy: decide aquariums that are in every, or no, combination making up the remainder
+    fn subset_sum_row(
+        &self,
+        iy: usize,
+        iteration: usize,
+        feasibility: &mut feasibility::RowFeasibility,
+        scratch: &mut LineScratch,
+    ) -> Vec<Deduction> {
+        let mut deductions = Vec::new();
+        let segments = &mut scratch.segments;
+        let first_ix = &mut scratch.first_ix;
+        segments.clear();
+        first_ix.clear();
+        first_ix.resize(self.n_partitions, None);
+        for ix in 0..self.width {
+            let cell = self.cell_at(ix, iy);
+            if cell.state == CellState::Empty && first_ix[cell.partition].is_none() {
+                first_ix[cell.partition] = Some(ix);
+                segments.push((cell.partition, self.row_partitions[iy][cell.partition]));
+            }
+        }
+        feasibility.update(segments);
+
+        let remainder = self.row_remaining(iy);
+        for (partition, can_flood, can_dry) in feasibility.options(remainder) {
+            let state = match (can_flood, can_dry) {
+                (false, true) => CellState::Invalid,
+                (true, false) => CellState::Flooded,
+                // Undecided, or the row can't be completed at all
+                _ => continue,
+            };
+            deductions.push(Deduction {
+                iteration,
+                rule: Rule::R5,
+                technique: Technique::SubsetSum,
+                ix: first_ix[partition].unwrap(),
+                iy,
+                state,
+                reason: format!(
+                    "No combination of the undecided aquariums in row {} adds up to the {} cells it needs {} aquarium {}",
+                    iy,
+                    remainder,
+                    if can_flood { "without" } else { "with" },
+                    self.partition_label(partition)
+                ),
+            });
+        }
+        deductions
+    }
+
+    /// Every band of two to `MAX_BAND` rows, and of one to `MAX_BAND` columns. A single row is
+    /// left to the subset-sum rule.
+    fn bands(&self) -> Vec<Band> {
+        let (height, width) = (self.height, self.width);
+        let rows = (0..height).flat_map(|top| {
+            (top + 1..height.min(top + MAX_BAND)).map(move |bottom| Band::Rows(top, bottom))
+        });
+        let cols = (0..width).flat_map(|left| {
+            (left..width.min(left + MAX_BAND)).map(move |right| Band::Cols(left, right))
+        });
+        rows.chain(cols).collect()
+    }
+
+    /// The rows of every aquarium and how deep its water can be, for the band rule
+    fn aquarium_rows(&self) -> Vec<AquariumRows> {
+        let mut aquariums: Vec<_> = (0..self.n_partitions)
+            .map(|_| AquariumRows {
+                rows: Vec::new(),
+                range: Some((0, 0)),
+            })
+            .collect();
+        let mut most = vec![usize::MAX; self.n_partitions];
+        // Bottom row first, so a cell's depth is the number of the aquarium's rows seen so far
+        for iy in (0..self.height).rev() {
+            for ix in 0..self.width {
+                let cell = self.cell_at(ix, iy);
+                let aquarium = &mut aquariums[cell.partition];
+                match aquarium.rows.last_mut() {
+                    Some((row, columns)) if *row == iy => columns.push(ix),
+                    _ => aquarium.rows.push((iy, vec![ix])),
+                }
+                let depth = aquarium.rows.len();
+                match cell.state {
+                    CellState::Empty => {}
+                    CellState::Flooded => {
+                        let (least, _) = aquarium.range.as_mut().unwrap();
+                        *least = (*least).max(depth);
+                    }
+                    CellState::Invalid => {
+                        most[cell.partition] = most[cell.partition].min(depth - 1);
+                    }
+                }
+            }
+        }
+        for (aquarium, most) in aquariums.iter_mut().zip(most) {
+            let least = aquarium.range.unwrap().0;
+            let most = most.min(aquarium.rows.len());
+            aquarium.range = (least <= most).then_some((least, most));
+        }
+        aquariums
+    }
+
+    /// R6 for a band: decide the levels of aquariums that every, or no, combination of levels
+    /// making up the band's hints fills past a row
+    fn band_count(
+        &self,
+        aquariums: &[AquariumRows],
+        band: Band,
+        iteration: usize,
+    ) -> Vec<Deduction> {
+        let mut deductions = Vec::new();
+        let total: isize = match band {
+            Band::Rows(top, bottom) => self.row_hints[top..=bottom].iter().sum(),
+            Band::Cols(left, right) => self.col_hints[left..=right].iter().sum(),
+        };
+        // What the decided aquariums leave for the rest, and the cells in the band each level of
+        // an undecided aquarium floods
+        let mut target = total;
+        let mut undecided = Vec::new();
+        let mut groups = Vec::new();
+        for (partition, aquarium) in aquariums.iter().enumerate() {
+            let Some((least, most)) = aquarium.range else {
+                // The board contradicts itself, which the search finds out
+                return deductions;
+            };
+            let mut in_band = 0;
+            let mut levels = vec![0];
+            for (iy, columns) in &aquarium.rows {
+                in_band += band.cells_in(*iy, columns);
+                levels.push(in_band);
+            }
+            if in_band == 0 {
+                continue;
+            }
+            if least == most {
+                target -= levels[least] as isize;
+            } else {
+                undecided.push(partition);
+                groups.push(levels[least..=most].to_vec());
+            }
+        }
+        let Ok(target) = usize::try_from(target) else {
+            return deductions;
+        };
+        if undecided.is_empty() {
+            return deductions;
+        }
+
+        let feasible = feasibility::feasible_choices(&groups, target);
+        for (&partition, feasible) in undecided.iter().zip(feasible) {
+            let aquarium = &aquariums[partition];
+            let (least, most) = aquarium.range.unwrap();
+            // No level works when the band can't be made up at all
+            let Some(first) = feasible.iter().position(|&it| it) else {
+                continue;
+            };
+            let last = feasible.iter().rposition(|&it| it).unwrap();
+            let mut push = |depth: usize, state: CellState, how: &str| {
+                let (iy, columns) = &aquarium.rows[depth - 1];
+                let (ix, iy) = (columns[0], *iy);
+                deductions.push(Deduction {
+                    iteration,
+                    rule: Rule::R6,
+                    technique: Technique::BandCount,
+                    ix,
+                    iy,
+                    state,
+                    reason: format!(
+                        "{} hold {} flooded cells, which the aquariums there can only make up with aquarium {} filled {} {} rows deep",
+                        band,
+                        total,
+                        self.partition_label(partition),
+                        how,
+                        depth - usize::from(state == CellState::Invalid)
+                    ),
+                });
+            };
+            if first > 0 {
+                push(least + first, CellState::Flooded, "at least");
+            }
+            if least + last < most {
+                push(least + last + 1, CellState::Invalid, "at most");
+            }
+        }
+        deductions
+    }
+
+    /// R3 and R4 for column ix: invalidate the top of aquariums with more undecided cells than the
+    /// column needs, and flood the bottom of aquariums the column can't do without
+    fn col_deductions(&self, ix: usize, iteration: usize) -> Vec<Deduction> {
+        let mut deductions = Vec::new();
+
+        // For each partion: the undecided and invalid cells in the column
+        let n_partitions = self.n_partitions;
+        let counts = &self.col_partition_counts[ix * n_partitions..(ix + 1) * n_partitions];
+        let total_empty = self.col_empty[ix];
+
+        let remainder = self.col_remaining(ix);
+        // println!("Col {} counts: {:?}", ix, col_empty);
+        //
+
+        let part_x = &self.col_partitions[ix];
+
+        for (partition, iy_list) in part_x.iter().enumerate() {
+            if iy_list.is_empty() {
+                continue;
+            }
+            let this_empty = counts[partition].empty;
+            let this_invalid = counts[partition].invalid;
+
+            let partition_extra = this_empty - remainder;
+            // println!("Col {}, Partition: {}, Extra: {}", ix, partition, partition_extra);
+
+            if partition_extra > 0
+                && self.hint_mode == HintMode::Exact
+                && self.rule_enabled(Rule::R3)
+            {
+                let invalid_cell_idx = this_invalid + partition_extra - 1;
+                // A contradictory board can point past the aquarium's cells
+                if let Some(&iy) = usize::try_from(invalid_cell_idx)
+                    .ok()
+                    .and_then(|i| iy_list.get(i))
+                {
+                    deductions.push(Deduction {
+                        iteration,
+                        rule: Rule::R3,
+                        state: Rule::R3.state(),
+                        technique: Technique::Overflow,
+                        ix,
+                        iy,
+                        reason: format!(
+                            "Column {} needs {} more flooded cells, but aquarium {} has {} undecided cells in the column",
+                            ix,
+                            remainder,
+                            self.partition_label(partition),
+                            this_empty
+                        ),
+                    });
+                }
+            }
+
+            let other_empty_count = total_empty - this_empty;
+            // Number of cells leftover if you assume all other empty cells get filled
+            let partition_required = remainder - other_empty_count;
+            if partition_required > 0 && self.rule_enabled(Rule::R4) {
+                let flood_cell_idx = this_invalid + (this_empty - partition_required);
+                // println!(
+                //     "col {}, part {}: req {}, other empt {}. Part idx {}",
+                //     ix, partition, partition_required, other_empty_count, flood_cell_idx
+                // );
+                if let Some(&iy) = usize::try_from(flood_cell_idx)
+                    .ok()
+                    .and_then(|i| iy_list.get(i))
+                {
+                    deductions.push(Deduction {
+                        iteration,
+                        rule: Rule::R4,
+                        state: Rule::R4.state(),
+                        technique: Technique::ForcedLevel,
+                        ix,
+                        iy,
+                        reason: format!(
+                            "Column {} needs {} more flooded cells, but only {} undecided cells lie outside aquarium {}",
+                            ix,
+                            remainder,
+                            other_empty_count,
+                            self.partition_label(partition)
+                        ),
+                    });
+                }
+            }
+        } // Partition loop
+        deductions
+    }
+
+    /// Whether the solver may use `rule`
+    fn rule_enabled(&self, rule: Rule) -> bool {
+        !self.disabled_rules.contains(&rule)
+    }
+
+    /// The row hint minus the number of cells flooded in row iy
+    fn row_remaining(&self, iy: usize) -> isize {
+        assert!(iy < self.height);
+        self.row_hints[iy] - self.flooded_rows.count(iy)
+    }
+
+    /// The column hint minus the number of cells flooded in column ix
+    fn col_remaining(&self, ix: usize) -> isize {
+        assert!(ix < self.width);
+        self.col_hints[ix] - self.flooded_cols.count(ix)
+    }
+
+    pub fn is_solved(&self) -> bool {
+        (0..self.height).all(|iy| self.hint_met(self.row_remaining(iy)))
+            && (0..self.width).all(|ix| self.hint_met(self.col_remaining(ix)))
+    }
+
+    /// Whether row iy has every cell decided and its hint met
+    fn row_finished(&self, iy: usize) -> bool {
+        self.row_empty[iy] == 0 && self.hint_met(self.row_remaining(iy))
+    }
+
+    /// Whether column ix has every cell decided and its hint met
+    fn col_finished(&self, ix: usize) -> bool {
+        self.col_empty[ix] == 0 && self.hint_met(self.col_remaining(ix))
+    }
+
+    /// Whether a line with `remaining` cells left to reach its hint satisfies it
+    fn hint_met(&self, remaining: isize) -> bool {
+        match self.hint_mode {
+            HintMode::Exact => remaining == 0,
+            HintMode::AtLeast => remaining <= 0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A board with the given aquarium numbers, one string of digits per row
+    fn layout(rows: &[&str]) -> Board {
+        let mut board = Board::make(rows[0].len(), rows.len());
+        for (iy, row) in rows.iter().enumerate() {
+            for (ix, digit) in row.chars().enumerate() {
+                board.cells[iy * board.width + ix].partition = digit.to_digit(10).unwrap() as usize;
+            }
+        }
+        board.index_partitions();
+        board
+    }
+
+    fn numbers(board: &Board) -> Vec<String> {
+        (0..board.height)
+            .map(|iy| {
+                (0..board.width)
+                    .map(|ix| board.partition_at(ix, iy).to_string())
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn a_board_built_from_numbers_is_the_same_puzzle() {
+        let b0 = Board::make_b0();
+        // Aquarium numbers needn't start at 0 or run on without gaps
+        let partitions: Vec<_> = (0..36)
+            .map(|i| 10 * b0.partition_at(i % 6, i / 6) + 5)
+            .collect();
+        let hints = |hints: &[isize]| -> Vec<usize> {
+            hints
+                .iter()
+                .map(|&hint| usize::try_from(hint).unwrap())
+                .collect()
+        };
+        let (row_hints, col_hints) = (hints(&b0.row_hints), hints(&b0.col_hints));
+        let mut board = Board::new(6, 6, &partitions, &row_hints, &col_hints).unwrap();
+        assert_eq!(numbers(&board), numbers(&b0));
+        board.solve();
+        assert_eq!(board.cells, Board::make_b0_solved().cells);
+
+        assert!(Board::new(6, 6, &partitions[1..], &row_hints, &col_hints).is_err());
+        assert!(Board::new(6, 6, &partitions, &row_hints[1..], &col_hints).is_err());
+        let mut too_many = row_hints.clone();
+        too_many[0] = 7;
+        assert!(Board::new(6, 6, &partitions, &too_many, &col_hints).is_err());
+        let mut uneven = row_hints.clone();
+        uneven[0] -= 1;
+        assert!(Board::new(6, 6, &partitions, &uneven, &col_hints).is_err());
+        assert!(Board::new(0, 0, &[], &[], &[]).is_err());
+    }
+
+    #[test]
+    fn removing_a_row_separates_the_pieces_it_joined() {
+        let mut board = layout(&["010", "010", "000"]);
+        board.partition_labels = vec!["a".to_string(), "b".to_string()];
+        board.remove_row(2);
+        assert_eq!(numbers(&board), ["012", "012"]);
+        assert_eq!(board.n_partitions, 3);
+        assert_eq!(board.partition_labels, ["a", "b", "2"]);
+    }
+
+    #[test]
+    fn removing_a_column_separates_the_pieces_it_joined() {
+        let mut board = layout(&["000", "110", "000"]);
+        board.remove_col(2);
+        assert_eq!(numbers(&board), ["00", "11", "22"]);
+        assert_eq!(board.n_partitions, 3);
+        assert_eq!(board.row_partitions[2], [0, 0, 2]);
+    }
+
+    #[test]
+    fn removing_a_line_keeps_whole_aquariums() {
+        let mut board = layout(&["001", "001", "221"]);
+        board.remove_row(0);
+        assert_eq!(numbers(&board), ["001", "221"]);
+        board.remove_col(0);
+        assert_eq!(numbers(&board), ["01", "21"]);
+    }
+
+    #[test]
+    fn at_least_hints_leave_out_the_rules_needing_exact_ones() {
+        let exact_only = [Rule::R1, Rule::R3, Rule::R5, Rule::R6];
+        let uses_exact_only = |deductions: &[Deduction]| {
+            deductions
+                .iter()
+                .any(|deduction| exact_only.contains(&deduction.rule))
+        };
+        let mut rng = generate::Rng::new(3);
+        let mut exact_used = false;
+        for _ in 0..20 {
+            let mut board = generate::generate(6, 6, &mut rng);
+            board.clear();
+            let mut trace = Vec::new();
+            board.clone().solve_traced(&mut trace);
+            exact_used |= uses_exact_only(&trace);
+
+            board.hint_mode = HintMode::AtLeast;
+            assert!(!uses_exact_only(&board.available_deductions()));
+            let mut trace = Vec::new();
+            board.solve_traced(&mut trace);
+            assert!(!uses_exact_only(&trace));
+        }
+        // The same puzzles with exact hints do use them
+        assert!(exact_used);
+    }
+
+    #[test]
+    fn the_band_count_gets_past_where_the_other_rules_stall() {
+        let text = "cols: 3 3 2 4 2 3\nrows: 0 2 3 4 5 3\n\
+                    0 0 0 1 1 2\n0 0 1 1 3 3\n4 0 5 1 3 3\n\
+                    4 4 5 5 3 3\n4 4 5 5 6 3\n7 7 7 6 6 8\n";
+        let board = format::parse(text).unwrap().board;
+        let mut solution = board.clone();
+        search::solve(&mut solution);
+        assert!(solution.is_solved());
+
+        let mut stalled = board.clone();
+        stalled.disabled_rules = vec![Rule::R6];
+        stalled.solve();
+        assert!(!stalled.is_solved());
+        assert!(stalled.available_deductions().is_empty());
+
+        stalled.disabled_rules.clear();
+        let deductions = stalled.available_deductions();
+        assert!(!deductions.is_empty());
+        for deduction in &deductions {
+            assert_eq!(deduction.rule, Rule::R6);
+            assert_eq!(
+                deduction.state,
+                solution.cell_state_at(deduction.ix, deduction.iy),
+                "{}",
+                deduction.reason
+            );
+        }
+        stalled.solve();
+        assert_eq!(stalled.cells, solution.cells);
+    }
+
+    /// Aquarium numbers run from 0 with none missing, labels are unique, and the indexes match
+    /// the cells
+    fn assert_consistent(board: &Board) {
+        let mut ids: Vec<_> = board.cells.iter().map(|cell| cell.partition).collect();
+        ids.sort_unstable();
+        ids.dedup();
+        assert_eq!(ids, (0..board.n_partitions).collect::<Vec<_>>());
+        let mut labels = board.partition_labels.clone();
+        labels.sort();
+        labels.dedup();
+        assert_eq!(labels.len(), board.partition_labels.len());
+        assert_eq!(labels.len(), board.n_partitions);
+
+        let mut rebuilt = board.clone();
+        rebuilt.index_partitions();
+        assert_eq!(rebuilt.cells, board.cells);
+        assert_eq!(rebuilt.partition_labels, board.partition_labels);
+        assert_eq!(rebuilt.row_partitions, board.row_partitions);
+        assert_eq!(rebuilt.col_partitions, board.col_partitions);
+    }
+
+    #[test]
+    fn merging_and_splitting_keep_numbers_and_labels_in_order() {
+        let mut board = layout(&["0011", "0011", "2233", "2233"]);
+        board.partition_labels = ["a", "b", "4", "d"].map(String::from).to_vec();
+
+        board.merge_partitions(0, 1).unwrap();
+        assert_eq!(numbers(&board), ["0000", "0000", "1122", "1122"]);
+        assert_eq!(board.partition_labels, ["a", "4", "d"]);
+        assert_consistent(&board);
+
+        board.split_partition(0, Cut::Right(1)).unwrap();
+        assert_eq!(numbers(&board), ["0033", "0033", "1122", "1122"]);
+        assert_eq!(board.partition_labels, ["a", "4", "d", "3"]);
+        assert_consistent(&board);
+
+        // "4" is taken, so the next new aquarium is labelled "5"
+        board.split_partition(1, Cut::Below(2)).unwrap();
+        assert_eq!(numbers(&board), ["0033", "0033", "1122", "4422"]);
+        assert_eq!(board.partition_labels, ["a", "4", "d", "3", "5"]);
+        assert_consistent(&board);
+
+        // The merged aquarium keeps the first one's label, and those after the second move down
+        board.merge_partitions(4, 2).unwrap();
+        assert_eq!(numbers(&board), ["0022", "0022", "1133", "3333"]);
+        assert_eq!(board.partition_labels, ["a", "4", "3", "5"]);
+        assert_consistent(&board);
+    }
+
+    #[test]
+    fn edits_that_cant_be_made_leave_the_board_alone() {
+        let mut board = layout(&["0011", "0011", "2233", "2233"]);
+        let before = board.clone();
+        assert!(board.merge_partitions(0, 3).is_err());
+        assert!(board.merge_partitions(1, 1).is_err());
+        assert!(board.split_partition(0, Cut::Below(1)).is_err());
+        assert!(board.split_partition(0, Cut::Right(2)).is_err());
+        assert_eq!(board.cells, before.cells);
+        assert_eq!(board.n_partitions, before.n_partitions);
+    }
+
+    /// The counts kept of the cells' states match those worked out again from the cells
+    fn assert_states_indexed(board: &Board) {
+        let mut rebuilt = board.clone();
+        rebuilt.index_states();
+        let counts = |board: &Board| -> Vec<_> {
+            board
+                .col_partition_counts
+                .iter()
+                .map(|counts| (counts.empty, counts.invalid))
+                .collect()
+        };
+        assert_eq!(rebuilt.row_empty, board.row_empty);
+        assert_eq!(rebuilt.col_empty, board.col_empty);
+        assert_eq!(counts(&rebuilt), counts(board));
+        for iy in 0..board.height {
+            assert_eq!(rebuilt.row_remaining(iy), board.row_remaining(iy));
+        }
+        for ix in 0..board.width {
+            assert_eq!(rebuilt.col_remaining(ix), board.col_remaining(ix));
+        }
+    }
+
+    #[test]
+    fn setting_a_water_level_fills_the_whole_aquarium() {
+        let b0 = Board::make_b0();
+        for partition in b0.partitions() {
+            let rows: Vec<_> = (0..b0.height)
+                .filter(|&iy| (0..b0.width).any(|ix| b0.partition_at(ix, iy) == partition))
+                .collect();
+            let levels = std::iter::once(None).chain(rows.iter().copied().map(Some));
+            for level in levels {
+                let mut board = b0.clone();
+                board.set_water_level(partition, level).unwrap();
+                for iy in 0..board.height {
+                    for ix in 0..board.width {
+                        let expected = if board.partition_at(ix, iy) != partition {
+                            CellState::Empty
+                        } else if level.is_some_and(|level| iy >= level) {
+                            CellState::Flooded
+                        } else {
+                            CellState::Invalid
+                        };
+                        assert_eq!(board.cell_state_at(ix, iy), expected);
+                    }
+                }
+                assert_states_indexed(&board);
+            }
+        }
+    }
+
+    #[test]
+    fn a_water_level_that_cant_be_set_changes_nothing() {
+        let mut board = Board::make_b0();
+        // Aquarium 0 lies in rows 0 to 2
+        board.set_water_level(0, Some(2)).unwrap();
+        let before = board.cells.clone();
+        assert!(board.set_water_level(0, None).is_err());
+        assert!(board.set_water_level(0, Some(1)).is_err());
+        assert!(board.set_water_level(0, Some(5)).is_err());
+        assert!(board.set_water_level(board.n_partitions, None).is_err());
+        assert_eq!(board.cells, before);
+        assert_states_indexed(&board);
+        board.set_water_level(0, Some(2)).unwrap();
+        assert_eq!(board.cells, before);
+    }
+}
@@ -0,0 +1,80 @@
+//! Markdown walkthrough of a solve, for writing up puzzles.
+
+use std::fmt::Write;
+
+use crate::{Board, Deduction};
+
+/// Solve a copy of `board` and describe each pass of the solver as a markdown section:
+/// the deductions made with their reasons, followed by the board as it stands after the pass.
+pub fn walkthrough(title: &str, board: &Board) -> String {
+    let mut solved = board.clone();
+    let mut trace = Vec::new();
+    let stats = solved.solve_traced(&mut trace);
+
+    let mut out = String::new();
+    writeln!(out, "# {}", title).unwrap();
+    writeln!(out).unwrap();
+    writeln!(out, "## Starting position").unwrap();
+    writeln!(out).unwrap();
+    write_board(&mut out, board);
+
+    // Replay the trace so the snapshots show the board between passes
+    let mut replay = board.clone();
+    for iteration in 1..=stats.iterations {
+        let pass: Vec<&Deduction> = trace
+            .iter()
+            .filter(|deduction| deduction.iteration == iteration)
+            .collect();
+        if pass.is_empty() {
+            continue;
+        }
+
+        writeln!(out, "## Pass {}", iteration).unwrap();
+        writeln!(out).unwrap();
+        for deduction in pass {
+            writeln!(
+                out,
+                "- **{:?}, {}** ({}, {}): {}. {} ({}, {}).",
+                deduction.rule,
+                deduction.technique.name(),
+                deduction.ix,
+                deduction.iy,
+                deduction.reason,
+                deduction.verb(),
+                deduction.ix,
+                deduction.iy,
+            )
+            .unwrap();
+            deduction.apply(&mut replay);
+        }
+        writeln!(out).unwrap();
+        write_board(&mut out, &replay);
+    }
+
+    writeln!(out, "## Result").unwrap();
+    writeln!(out).unwrap();
+    if solved.is_solved() {
+        writeln!(
+            out,
+            "Solved in {} passes with {} deductions.",
+            stats.iterations, stats.deductions
+        )
+        .unwrap();
+    } else {
+        writeln!(
+            out,
+            "The rules stall after {} deductions; the puzzle needs a guess from here.",
+            stats.deductions
+        )
+        .unwrap();
+    }
+
+    out
+}
+
+fn write_board(out: &mut String, board: &Board) {
+    writeln!(out, "```text").unwrap();
+    out.push_str(&board.render());
+    writeln!(out, "```").unwrap();
+    writeln!(out).unwrap();
+}
@@ -0,0 +1,26 @@
+//! Hooks for watching a solve as it happens, e.g. to draw it live or log it.
+
+use crate::{Board, Deduction};
+
+/// Told about each step of a solve. Every method does nothing by default.
+pub trait SolverObserver {
+    /// A rule decided a cell, and `board` already shows it. Return false to stop the solve here.
+    fn on_deduction(&mut self, _board: &Board, _deduction: &Deduction) -> bool {
+        true
+    }
+
+    /// A pass over all of the rules finished. `updated` is whether it changed the board.
+    fn on_iteration_end(&mut self, _board: &Board, _iteration: usize, _updated: bool) {}
+
+    /// The search is about to try filling aquarium `partition` to `level`, where None leaves it
+    /// unfilled. Return false to stop the search here.
+    fn on_guess(&mut self, _partition: usize, _level: Option<usize>) -> bool {
+        true
+    }
+
+    /// The search undid its guess for aquarium `partition`
+    fn on_backtrack(&mut self, _partition: usize, _level: Option<usize>) {}
+}
+
+/// Watches nothing
+impl SolverObserver for () {}
@@ -0,0 +1,87 @@
+//! Check the rules against a known solution, to catch unsound deductions.
+
+use crate::observer::SolverObserver;
+use crate::stats::SolveStats;
+use crate::{Board, CellState, Deduction};
+
+/// The first deduction that disagrees with the solution
+pub struct Contradiction {
+    /// Position of the deduction in the trace
+    pub index: usize,
+    pub deduction: Deduction,
+    /// The board just before the deduction
+    pub before: Board,
+    /// The cells the deduction got wrong, as (ix, iy)
+    pub cells: Vec<(usize, usize)>,
+}
+
+/// Solve a copy of `board` with the rules and check, after every deduction, that each decided
+/// cell agrees with `solution`. Returns the first deduction that doesn't.
+pub fn check(
+    board: &Board,
+    solution: &Board,
+) -> Result<(SolveStats, Option<Contradiction>), String> {
+    if (board.width, board.height) != (solution.width, solution.height) {
+        return Err("The solution is a different size from the puzzle".to_string());
+    }
+    let same_layout = (0..board.height).all(|iy| {
+        (0..board.width).all(|ix| board.partition_at(ix, iy) == solution.partition_at(ix, iy))
+    });
+    if !same_layout
+        || board.row_hints != solution.row_hints
+        || board.col_hints != solution.col_hints
+    {
+        return Err("The solution is for a different puzzle".to_string());
+    }
+    if !solution.is_solved() {
+        return Err("The solution is not solved".to_string());
+    }
+
+    // Stop at the first bad deduction, before it can send the rules astray
+    let mut trace = Vec::new();
+    let mut solved = board.clone();
+    let mut checker = Checker { solution };
+    let stats = solved.solve_observed(&mut trace, &mut checker);
+
+    let cells = checker.wrong_cells(&solved);
+    if cells.is_empty() {
+        return Ok((stats, None));
+    }
+    let deduction = trace.pop().unwrap();
+    let mut before = board.clone();
+    for earlier in &trace {
+        earlier.apply(&mut before);
+    }
+    Ok((
+        stats,
+        Some(Contradiction {
+            index: trace.len(),
+            deduction,
+            before,
+            cells,
+        }),
+    ))
+}
+
+/// Stops the solve once any decided cell disagrees with the solution
+struct Checker<'a> {
+    solution: &'a Board,
+}
+
+impl Checker<'_> {
+    fn wrong_cells(&self, board: &Board) -> Vec<(usize, usize)> {
+        (0..board.height)
+            .flat_map(|iy| (0..board.width).map(move |ix| (ix, iy)))
+            .filter(|&(ix, iy)| {
+                let state = board.cell_state_at(ix, iy);
+                state != CellState::Empty && state != self.solution.cell_state_at(ix, iy)
+            })
+            .collect()
+    }
+}
+
+impl SolverObserver for Checker<'_> {
+    fn on_deduction(&mut self, board: &Board, _deduction: &Deduction) -> bool {
+        self.wrong_cells(board).is_empty()
+    }
+}
@@ -0,0 +1,43 @@
+//! The rule passes' parallel iterators: rayon's with `std`, and plain iterators standing in for
+//! them without it, so a pass is written the same way either way.
+
+#[cfg(feature = "std")]
+pub use rayon::prelude::*;
+
+#[cfg(not(feature = "std"))]
+pub use sequential::*;
+
+#[cfg(not(feature = "std"))]
+mod sequential {
+    pub trait IntoParallelIterator: IntoIterator + Sized {
+        fn into_par_iter(self) -> Self::IntoIter {
+            self.into_iter()
+        }
+    }
+
+    impl<I: IntoIterator> IntoParallelIterator for I {}
+
+    pub trait ParallelSliceMut<T> {
+        fn par_iter_mut(&mut self) -> core::slice::IterMut<'_, T>;
+    }
+
+    impl<T> ParallelSliceMut<T> for [T] {
+        fn par_iter_mut(&mut self) -> core::slice::IterMut<'_, T> {
+            self.iter_mut()
+        }
+    }
+
+    pub trait ParallelIterator: Iterator + Sized {
+        /// `map`, with one scratch value made by `init` for the whole run
+        fn map_init<S, R>(
+            self,
+            init: impl FnOnce() -> S,
+            mut f: impl FnMut(&mut S, Self::Item) -> R,
+        ) -> impl Iterator<Item = R> {
+            let mut scratch = init();
+            self.map(move |item| f(&mut scratch, item))
+        }
+    }
+
+    impl<I: Iterator> ParallelIterator for I {}
+}
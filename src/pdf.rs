@@ -0,0 +1,342 @@
+//! Printable PDF of blank puzzles, one puzzle per page, or a booklet of several puzzles per page
+//! followed by their solutions.
+//!
+//! The PDF is written by hand: a page tree, one content stream per page and the built-in
+//! Helvetica font, which every reader has.
+
+use std::fmt::Write;
+
+use crate::{Board, CellState};
+
+/// Paper to lay the pages out for
+#[derive(Copy, Clone, Debug)]
+pub enum PageSize {
+    A4,
+    Letter,
+}
+
+impl PageSize {
+    pub fn from_name(name: &str) -> Option<PageSize> {
+        match name.to_lowercase().as_str() {
+            "a4" => Some(PageSize::A4),
+            "letter" => Some(PageSize::Letter),
+            _ => None,
+        }
+    }
+
+    /// Width and height in points
+    fn dimensions(self) -> (f64, f64) {
+        match self {
+            PageSize::A4 => (595.28, 841.89),
+            PageSize::Letter => (612.0, 792.0),
+        }
+    }
+}
+
+const MARGIN: f64 = 54.0;
+const TITLE_SIZE: f64 = 18.0;
+const MAX_CELL: f64 = 48.0;
+/// Title size in a booklet, where the puzzles share pages
+const PANEL_TITLE_SIZE: f64 = 12.0;
+/// Room left between the puzzles sharing a page
+const GUTTER: f64 = 18.0;
+/// Solutions are printed smaller than the puzzles, this many to a page
+const SOLUTIONS_PER_PAGE: usize = 9;
+const THIN_LINE: f64 = 0.5;
+const THICK_LINE: f64 = 2.5;
+
+/// Lay out each puzzle on its own page: the title, the grid with thick walls between the aquariums,
+/// and the hints. Only the layout and hints of the boards are drawn, never their cell states.
+pub fn puzzles_pdf(puzzles: &[(&str, &Board)], page_size: PageSize) -> Vec<u8> {
+    let (page_width, page_height) = page_size.dimensions();
+    let pages: Vec<_> = puzzles
+        .iter()
+        .map(|&(title, board)| {
+            let mut out = String::new();
+            let area = Area {
+                left: MARGIN,
+                top: page_height - MARGIN,
+                width: page_width - 2.0 * MARGIN,
+                height: page_height - 2.0 * MARGIN,
+            };
+            draw_board(&mut out, title, board, false, area, TITLE_SIZE);
+            out
+        })
+        .collect();
+    document(&pages, page_size)
+}
+
+/// A booklet: the puzzles numbered and laid out `per_page` to a page, two columns across once
+/// there are more than two, followed by a solutions section with each puzzle's water filled in.
+/// `solutions` holds the solved board for each puzzle, or `None` where there is none.
+pub fn booklet_pdf(
+    puzzles: &[(&str, &Board)],
+    solutions: &[Option<Board>],
+    page_size: PageSize,
+    per_page: usize,
+) -> Vec<u8> {
+    let numbered: Vec<_> = puzzles
+        .iter()
+        .enumerate()
+        .map(|(i, (title, _))| format!("{}. {}", i + 1, title))
+        .collect();
+    let mut pages = Vec::new();
+    let puzzle_panels: Vec<_> = puzzles
+        .iter()
+        .zip(&numbered)
+        .map(|(&(_, board), title)| (title.clone(), board, false))
+        .collect();
+    for chunk in puzzle_panels.chunks(per_page.max(1)) {
+        pages.push(panel_page(chunk, per_page, None, page_size));
+    }
+
+    let solution_panels: Vec<_> = puzzles
+        .iter()
+        .zip(solutions)
+        .zip(&numbered)
+        .map(|((&(_, board), solution), title)| match solution {
+            Some(solution) => (title.clone(), solution, true),
+            None => (format!("{} (no solution)", title), board, false),
+        })
+        .collect();
+    for (i, chunk) in solution_panels.chunks(SOLUTIONS_PER_PAGE).enumerate() {
+        let heading = if i == 0 { Some("Solutions") } else { None };
+        pages.push(panel_page(chunk, SOLUTIONS_PER_PAGE, heading, page_size));
+    }
+    document(&pages, page_size)
+}
+
+/// Part of a page to draw a board in, in points from the bottom left of the page
+#[derive(Copy, Clone, Debug)]
+struct Area {
+    left: f64,
+    top: f64,
+    width: f64,
+    height: f64,
+}
+
+/// The drawing operators for a page of up to `slots` boards, each given as its title, the board
+/// and whether to fill in its water, under an optional page heading
+fn panel_page(
+    panels: &[(String, &Board, bool)],
+    slots: usize,
+    heading: Option<&str>,
+    page_size: PageSize,
+) -> String {
+    let (page_width, page_height) = page_size.dimensions();
+    let mut out = String::new();
+    let mut top = page_height - MARGIN;
+    if let Some(heading) = heading {
+        text(
+            &mut out,
+            heading,
+            page_width / 2.0,
+            top - TITLE_SIZE,
+            TITLE_SIZE,
+        );
+        top -= 2.0 * TITLE_SIZE;
+    }
+
+    let columns = match slots {
+        0..=2 => 1,
+        3..=8 => 2,
+        _ => 3,
+    };
+    let rows = slots.div_ceil(columns);
+    let slot_width = (page_width - 2.0 * MARGIN - GUTTER * (columns - 1) as f64) / columns as f64;
+    let slot_height = (top - MARGIN - GUTTER * (rows - 1) as f64) / rows as f64;
+    for (i, (title, board, water)) in panels.iter().enumerate() {
+        let area = Area {
+            left: MARGIN + (slot_width + GUTTER) * (i % columns) as f64,
+            top: top - (slot_height + GUTTER) * (i / columns) as f64,
+            width: slot_width,
+            height: slot_height,
+        };
+        draw_board(&mut out, title, board, *water, area, PANEL_TITLE_SIZE);
+    }
+    out
+}
+
+/// A PDF with a page for each content stream
+fn document(pages: &[String], page_size: PageSize) -> Vec<u8> {
+    let (page_width, page_height) = page_size.dimensions();
+
+    // Objects 1 and 2 are the catalog and page tree, 3 is the font,
+    // and each page is a page object followed by its content stream
+    let page_ids: Vec<usize> = (0..pages.len()).map(|i| 4 + 2 * i).collect();
+    let mut objects = vec![
+        "<< /Type /Catalog /Pages 2 0 R >>".to_string(),
+        format!(
+            "<< /Type /Pages /Kids [{}] /Count {} >>",
+            page_ids
+                .iter()
+                .map(|id| format!("{} 0 R", id))
+                .collect::<Vec<_>>()
+                .join(" "),
+            pages.len()
+        ),
+        "<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica >>".to_string(),
+    ];
+    for (content, &page_id) in pages.iter().zip(&page_ids) {
+        objects.push(format!(
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {:.2} {:.2}] \
+             /Resources << /Font << /F1 3 0 R >> >> /Contents {} 0 R >>",
+            page_width,
+            page_height,
+            page_id + 1
+        ));
+        objects.push(format!(
+            "<< /Length {} >>\nstream\n{}endstream",
+            content.len(),
+            content
+        ));
+    }
+
+    let mut out = String::from("%PDF-1.4\n");
+    let mut offsets = Vec::new();
+    for (i, object) in objects.iter().enumerate() {
+        offsets.push(out.len());
+        write!(out, "{} 0 obj\n{}\nendobj\n", i + 1, object).unwrap();
+    }
+    let xref = out.len();
+    writeln!(out, "xref\n0 {}", objects.len() + 1).unwrap();
+    writeln!(out, "0000000000 65535 f ").unwrap();
+    for offset in offsets {
+        writeln!(out, "{:010} 00000 n ", offset).unwrap();
+    }
+    writeln!(
+        out,
+        "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF",
+        objects.len() + 1,
+        xref
+    )
+    .unwrap();
+    out.into_bytes()
+}
+
+/// The drawing operators for one board in `area`: its title, the flooded cells shaded if `water`
+/// is set, the grid with thick walls between the aquariums, and the hints
+fn draw_board(
+    out: &mut String,
+    title: &str,
+    board: &Board,
+    water: bool,
+    area: Area,
+    title_size: f64,
+) {
+    let width = board.width as f64;
+    let height = board.height as f64;
+
+    // Leave a cell's worth of room above and to the left of the grid for the hints
+    let cell = (area.width / (width + 1.0))
+        .min((area.height - 2.0 * title_size) / (height + 1.0))
+        .min(MAX_CELL);
+    let grid_width = cell * width;
+    let left = area.left + (area.width - grid_width + cell) / 2.0;
+    let top = area.top - 2.0 * title_size - cell;
+    let x = |ix: usize| left + cell * ix as f64;
+    let y = |iy: usize| top - cell * iy as f64;
+
+    text(
+        out,
+        title,
+        area.left + area.width / 2.0,
+        area.top - title_size,
+        title_size,
+    );
+
+    let hint_size = (cell * 0.5).min(16.0);
+    for (ix, hint) in board.col_hints.iter().enumerate() {
+        let label = hint.to_string();
+        text(out, &label, x(ix) + cell / 2.0, top + cell * 0.3, hint_size);
+    }
+    for (iy, hint) in board.row_hints.iter().enumerate() {
+        let label = hint.to_string();
+        text(
+            out,
+            &label,
+            left - cell / 2.0,
+            y(iy + 1) + cell * 0.3,
+            hint_size,
+        );
+    }
+
+    if water {
+        let mut fill = String::new();
+        for iy in 0..board.height {
+            for ix in 0..board.width {
+                if board.cell_state_at(ix, iy) == CellState::Flooded {
+                    writeln!(
+                        fill,
+                        "{:.2} {:.2} {:.2} {:.2} re",
+                        x(ix),
+                        y(iy + 1),
+                        cell,
+                        cell
+                    )
+                    .unwrap();
+                }
+            }
+        }
+        writeln!(out, "0.65 0.8 1 rg\n{}f", fill).unwrap();
+    }
+
+    // Light lines between every cell, then heavy ones over the aquarium walls and the edge
+    let mut thin = String::new();
+    let mut thick = String::new();
+    for iy in 0..board.height {
+        for ix in 0..board.width - 1 {
+            let path = if board.wall_at(ix, iy) {
+                &mut thick
+            } else {
+                &mut thin
+            };
+            line(path, x(ix + 1), y(iy), x(ix + 1), y(iy + 1));
+        }
+    }
+    for iy in 0..board.height - 1 {
+        for ix in 0..board.width {
+            let path = if board.floor_at(ix, iy) {
+                &mut thick
+            } else {
+                &mut thin
+            };
+            line(path, x(ix), y(iy + 1), x(ix + 1), y(iy + 1));
+        }
+    }
+    writeln!(
+        thick,
+        "{:.2} {:.2} {:.2} {:.2} re",
+        left,
+        y(board.height),
+        grid_width,
+        cell * height
+    )
+    .unwrap();
+
+    writeln!(out, "0.6 G {} w\n{}S", THIN_LINE, thin).unwrap();
+    writeln!(out, "0 G {} w 2 J\n{}S", THICK_LINE, thick).unwrap();
+}
+
+fn line(out: &mut String, x0: f64, y0: f64, x1: f64, y1: f64) {
+    writeln!(out, "{:.2} {:.2} m {:.2} {:.2} l", x0, y0, x1, y1).unwrap();
+}
+
+/// Centre `label` horizontally on `x`, with its baseline at `y`
+fn text(out: &mut String, label: &str, x: f64, y: f64, size: f64) {
+    // Helvetica digits are 0.556 em wide; close enough for the other characters in a title
+    let label_width = 0.556 * size * label.chars().count() as f64;
+    let escaped = label
+        .replace('\\', "\\\\")
+        .replace('(', "\\(")
+        .replace(')', "\\)");
+    writeln!(
+        out,
+        "BT /F1 {:.2} Tf {:.2} {:.2} Td ({}) Tj ET",
+        size,
+        x - label_width / 2.0,
+        y,
+        escaped
+    )
+    .unwrap();
+}
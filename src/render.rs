@@ -0,0 +1,456 @@
+//! Drawing a board as text, for the terminal and for anything else that shows it as it stands.
+
+use std::fmt::Write;
+use std::ops::Range;
+
+use crate::{describe, search, Board, Cell, CellState, FloorState, WallState};
+
+/// What `Board::render_with` draws in the cells and margins
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub enum RenderMode {
+    /// The unsolved puzzle: walls and hints only
+    Puzzle,
+    /// The cells as they stand, with the count each line still needs
+    Working,
+    /// The board once solved, with the search finishing what the rules can't
+    Solution,
+    /// The cells as they stand, followed by how many rows deep each aquarium can still be filled
+    Pencil,
+}
+
+impl RenderMode {
+    pub fn from_name(name: &str) -> Option<RenderMode> {
+        match name {
+            "puzzle" => Some(RenderMode::Puzzle),
+            "working" => Some(RenderMode::Working),
+            "solution" => Some(RenderMode::Solution),
+            "pencil" => Some(RenderMode::Pencil),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct RenderOptions {
+    pub mode: RenderMode,
+    /// Show each cell's partition id next to its state
+    pub show_partitions: bool,
+    /// Label the rows and columns with their indices
+    pub show_index: bool,
+    /// Keep to printable 7-bit ASCII, with no escape codes. The board itself is always drawn in
+    /// ASCII; this also replaces any other character in the labels a puzzle file gave with `?`.
+    pub ascii: bool,
+    /// Describe the board row by row in sentences instead of drawing it, for screen readers
+    pub linear: bool,
+    /// The most characters wide the drawing may be. A board too wide is drawn with a character
+    /// per cell, and split into bands of columns if that's still too wide.
+    pub max_width: Option<usize>,
+}
+
+impl Default for RenderOptions {
+    fn default() -> RenderOptions {
+        RenderOptions {
+            mode: RenderMode::Working,
+            show_partitions: false,
+            show_index: false,
+            ascii: false,
+            linear: false,
+            max_width: None,
+        }
+    }
+}
+
+/// How wide the terminal the output goes to is, if it goes to one. Worked out on the first
+/// call only, as asking `stty` starts a process.
+fn terminal_width() -> Option<usize> {
+    static WIDTH: std::sync::OnceLock<Option<usize>> = std::sync::OnceLock::new();
+    *WIDTH.get_or_init(measure_terminal_width)
+}
+
+/// `COLUMNS` if the shell exports it, or else what `stty` says of the terminal
+fn measure_terminal_width() -> Option<usize> {
+    use std::io::IsTerminal;
+    if !std::io::stdout().is_terminal() {
+        return None;
+    }
+    if let Some(columns) = std::env::var("COLUMNS")
+        .ok()
+        .and_then(|columns| columns.parse().ok())
+    {
+        return Some(columns);
+    }
+    // `stty size` prints the rows and columns of the terminal on its standard input
+    let tty = std::fs::File::open("/dev/tty").ok()?;
+    let output = std::process::Command::new("stty")
+        .arg("size")
+        .stdin(tty)
+        .output()
+        .ok()?;
+    let size = String::from_utf8(output.stdout).ok()?;
+    size.split_whitespace().nth(1)?.parse().ok()
+}
+
+/// The text with every character but printable 7-bit ASCII and newlines replaced with `?`.
+/// One `?` per character keeps the columns of a drawn board lined up.
+fn to_ascii(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            '\n' | ' '..='~' => c,
+            _ => '?',
+        })
+        .collect()
+}
+
+/// Numbers written down the page a digit to a line, right-aligned so the units share the bottom
+/// line, under every other character after the margin: where the cells of a compact board are
+fn write_down(out: &mut String, margin: &str, numbers: &[String]) {
+    let digits = numbers.iter().map(|number| number.len()).max().unwrap_or(0);
+    for line in 0..digits {
+        write!(out, "{} ", margin).unwrap();
+        for number in numbers {
+            let padded = format!("{:>width$}", number, width = digits);
+            write!(out, "{} ", &padded[line..line + 1]).unwrap();
+        }
+        writeln!(out).unwrap();
+    }
+}
+
+impl Board {
+    pub fn render(&self) -> String {
+        self.render_with(RenderOptions::default())
+    }
+
+    pub fn render_with(&self, options: RenderOptions) -> String {
+        if options.mode == RenderMode::Solution && !self.is_solved() {
+            let mut solved = self.clone();
+            search::solve(&mut solved);
+            return solved.render_with(options);
+        }
+        if options.linear {
+            let out = describe::describe(self, options);
+            return if options.ascii { to_ascii(&out) } else { out };
+        }
+
+        let (mut out, width) = match options.max_width {
+            Some(max_width) => self.draw_within(options, max_width),
+            None => self.draw(options, 0..self.width, false),
+        };
+
+        // Depths: 'Depths: A: 0-2  B: 1  C: 0-3', wrapped to the board's width
+        if options.mode == RenderMode::Pencil {
+            let header = "Depths:";
+            let mut line = header.to_string();
+            for partition in self.partitions() {
+                let depths = match self.depth_range(partition) {
+                    Some((least, most)) if least == most => least.to_string(),
+                    Some((least, most)) => format!("{}-{}", least, most),
+                    None => "none".to_string(),
+                };
+                let entry = format!("{}: {}", self.partition_label(partition), depths);
+                if line.len() == header.len() {
+                    line += " ";
+                } else if line.len() + 2 + entry.len() > width {
+                    writeln!(out, "{}", line).unwrap();
+                    line = " ".repeat(header.len() + 1);
+                } else {
+                    line += "  ";
+                }
+                line += &entry;
+            }
+            writeln!(out, "{}", line).unwrap();
+        }
+
+        if options.ascii {
+            out = to_ascii(&out);
+        }
+        out
+    }
+
+    /// Draw the hints, walls and cells of the columns in `columns`, with a character per cell
+    /// and no aquarium labels if `compact`, along with the board's width in characters, margin
+    /// and all. Where the columns cut through the board, the edge is drawn as `:` beside a row
+    /// whose aquarium carries on across the cut.
+    fn draw(
+        &self,
+        options: RenderOptions,
+        columns: Range<usize>,
+        compact: bool,
+    ) -> (String, usize) {
+        let mut out = String::new();
+        let print_partitions = options.show_partitions;
+        let print_index = options.show_index;
+        let print_cells = options.mode != RenderMode::Puzzle;
+        let print_remaining = matches!(options.mode, RenderMode::Working | RenderMode::Pencil);
+
+        // Cells widen to fit the longest label
+        let cell_width = if compact {
+            1
+        } else if print_partitions {
+            let longest = (0..self.n_partitions)
+                .map(|partition| self.partition_label(partition).chars().count())
+                .max()
+                .unwrap_or(0);
+            Cell::rep_width().max(1 + longest)
+        } else {
+            Cell::rep_width()
+        };
+        let column_gap = " ".repeat(cell_width - 1);
+        let wall_width = 1;
+
+        let board_width = wall_width + (cell_width + wall_width) * columns.len();
+        let board_bounds = "#".repeat(board_width);
+
+        let left_margin = "   ";
+
+        let right_clue_width = 4;
+        let right_clue_space = " ".repeat(right_clue_width);
+
+        // Todo: Use format width for numbers
+
+        // print top
+        // '   N0  N1 N3'
+        let numbers = |number: &dyn Fn(usize) -> isize| -> Vec<String> {
+            columns.clone().map(|ix| number(ix).to_string()).collect()
+        };
+        if compact {
+            write_down(&mut out, left_margin, &numbers(&|ix| self.col_hints[ix]));
+        } else {
+            write!(out, "{} ", left_margin).unwrap();
+            for hint in &self.col_hints[columns.clone()] {
+                write!(out, "{:>2}{}", hint, column_gap).unwrap();
+            }
+            writeln!(out).unwrap();
+        }
+
+        // '  #########'
+        write!(out, "{}{}", left_margin, board_bounds).unwrap();
+        writeln!(out).unwrap();
+
+        //
+        for iy in 0..self.height {
+            // Cell row: 'N # C0 W0 C1 W1 C2 # M' ? ' |  I'
+            // i.e. '3 #   |   #X  #*  # 2'
+            // i.e. '3 #* 0|* 0#X 1#  2# 1 | 1'
+
+            // Left Margin: 'N #', with ':' instead at a cut the aquarium carries on across
+            let left_edge = if columns.start > 0 && !self.wall_at(columns.start - 1, iy) {
+                ':'
+            } else {
+                '#'
+            };
+            let right_edge = if columns.end < self.width && !self.wall_at(columns.end - 1, iy) {
+                ':'
+            } else {
+                '#'
+            };
+            write!(out, "{:>2} {}", self.row_hints[iy], left_edge).unwrap();
+            //
+            let row_cells = {
+                let row_offset = iy * self.width;
+                &self.cells[row_offset..row_offset + self.width]
+            };
+
+            // Cells and walls: 'C0 W0 C1 W1 C2'
+            for ix in columns.clone() {
+                let cell = if print_cells {
+                    row_cells[ix]
+                } else {
+                    Cell {
+                        state: CellState::Empty,
+                        ..row_cells[ix]
+                    }
+                };
+                let label = if print_partitions {
+                    Some(self.partition_label(cell.partition))
+                } else {
+                    None
+                };
+                if compact {
+                    write!(out, "{}", cell.state.rep()).unwrap();
+                } else {
+                    write!(out, "{}", cell.rep(label.as_deref(), cell_width)).unwrap();
+                }
+
+                if ix + 1 != columns.end {
+                    let wall = self.wall_at(ix, iy);
+                    // A compact board only draws the aquariums' walls, as thin lines between
+                    // every cell would crowd them out
+                    let rep = if compact && !wall {
+                        ' '
+                    } else {
+                        WallState::rep_bool(wall)
+                    };
+                    write!(out, "{}", rep).unwrap();
+                }
+            }
+
+            // Close row and remainder: '# M'
+            if print_remaining {
+                write!(out, "{} {:>2}", right_edge, self.row_remaining(iy)).unwrap();
+            } else if print_index {
+                write!(out, "{}   ", right_edge).unwrap();
+            } else {
+                write!(out, "{}", right_edge).unwrap();
+            }
+
+            // Row index: ' | I'
+            if print_index {
+                write!(out, " |  {:>2}", iy).unwrap();
+            }
+
+            writeln!(out).unwrap();
+            //
+            // Floor row: '  # F0 J0 F1 J1 #' ? '   |'
+            // i.e. '  #---+---#####---#'
+            // i.e. '  #---+---#####---#   |'
+            if iy + 1 != self.height {
+                // Left margin: '  #'
+                write!(out, "{}#", left_margin).unwrap();
+                for ix in columns.clone() {
+                    let it = &self.floor_at(ix, iy);
+                    let rep = if compact && !*it {
+                        ' '
+                    } else {
+                        FloorState::rep_bool(*it)
+                    };
+                    let rep = rep.to_string().repeat(cell_width);
+
+                    // Up, Left (this), Right, Down
+                    let junction_neighbors = [
+                        if ix + 1 == columns.end {
+                            true
+                        } else {
+                            self.wall_at(ix, iy)
+                        },
+                        *it,
+                        if ix + 1 == columns.end {
+                            true
+                        } else {
+                            self.floor_at(ix + 1, iy)
+                        },
+                        if iy + 1 == self.height || ix + 1 == columns.end {
+                            true
+                        } else {
+                            self.wall_at(ix, iy + 1)
+                        },
+                    ];
+                    let count = junction_neighbors.iter().filter(|&&x| x).count();
+                    // `if any` would have been just as fine I guess
+                    let junction = match count {
+                        0 | 1 if compact => ' ',
+                        0 | 1 => '+',
+                        _ => '#',
+                    };
+
+                    // Cell floor and junction: 'F_ix J_ix'
+                    write!(out, "{}{}", rep, junction).unwrap();
+                }
+                if print_index {
+                    // '   |'
+                    write!(out, "{}|", right_clue_space).unwrap();
+                }
+                writeln!(out).unwrap();
+            }
+        }
+
+        // Print bottom: '  #########' ? '  |'
+        write!(out, "{}{}", left_margin, board_bounds).unwrap();
+        if print_index {
+            write!(out, "{}|", right_clue_space).unwrap();
+        }
+        writeln!(out).unwrap();
+
+        // Counts: '     M0 M1 M3' ? '   |'
+        if print_remaining && compact {
+            write_down(
+                &mut out,
+                left_margin,
+                &numbers(&|ix| self.col_remaining(ix)),
+            );
+        } else if print_remaining {
+            write!(out, "{} ", left_margin).unwrap();
+            for ix in columns.clone() {
+                write!(out, "{:>2}{}", self.col_remaining(ix), column_gap).unwrap();
+            }
+
+            if print_index {
+                write!(out, "{}|", right_clue_space).unwrap();
+            }
+
+            writeln!(out).unwrap();
+        }
+        //
+        //
+        if print_index {
+            // Axis line: '   _________|'
+            write!(
+                out,
+                "{}{}|",
+                left_margin,
+                "_".repeat(board_width + right_clue_width)
+            )
+            .unwrap();
+            writeln!(out).unwrap();
+
+            // Axis labels: '    0  1  2  3'
+            if compact {
+                write_down(&mut out, left_margin, &numbers(&|ix| ix as isize));
+            } else {
+                write!(out, "{} ", left_margin).unwrap();
+                for ix in columns.clone() {
+                    write!(out, "{:>2}{}", ix, column_gap).unwrap();
+                }
+                writeln!(out).unwrap();
+            }
+        }
+
+        (out, left_margin.len() + board_width)
+    }
+
+    /// Draw the board no more than `max_width` characters wide if it can be: as usual, or else
+    /// compactly, or else compactly in bands of columns one under another, each with the row hints
+    /// again. Returns the drawing and the width of its widest board.
+    fn draw_within(&self, options: RenderOptions, max_width: usize) -> (String, usize) {
+        let fits = |(drawing, _): &(String, usize)| {
+            drawing
+                .lines()
+                .all(|line| line.chars().count() <= max_width)
+        };
+        for compact in [false, true] {
+            let drawing = self.draw(options, 0..self.width, compact);
+            if fits(&drawing) {
+                return drawing;
+            }
+        }
+        let band = (1..self.width)
+            .rev()
+            .find(|&n| fits(&self.draw(options, 0..n, true)))
+            .unwrap_or(1);
+        let mut out = String::new();
+        let mut width = 0;
+        for start in (0..self.width).step_by(band) {
+            let end = (start + band).min(self.width);
+            let (drawing, band_width) = self.draw(options, start..end, true);
+            if start > 0 {
+                writeln!(out).unwrap();
+            }
+            writeln!(out, "Columns {} to {}:", start, end - 1).unwrap();
+            out += &drawing;
+            width = width.max(band_width);
+        }
+        (out, width)
+    }
+
+    pub fn print(&self) {
+        self.print_with(RenderOptions::default());
+    }
+
+    /// Print the board, fitted to the width of the terminal unless `options` gives one
+    pub fn print_with(&self, options: RenderOptions) {
+        let options = RenderOptions {
+            max_width: options.max_width.or_else(terminal_width),
+            ..options
+        };
+        print!("{}", self.render_with(options));
+    }
+}
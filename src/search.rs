@@ -0,0 +1,1144 @@
+//! Backtracking search over aquarium water levels, for when the rules stall.
+//!
+//! Each aquarium is a variable whose value is its water level. After every assignment the rows
+//! and columns the aquarium touches are checked against their hints, using the least and most water
+//! the unassigned aquariums could still add. When every level of an aquarium fails, the assignments
+//! responsible are recorded as a nogood so the same combination is pruned anywhere else in the search,
+//! and the search jumps straight back to the most recent aquarium involved.
+//!
+//! Levels that no longer fit a line are ruled out as soon as they stop fitting, which tightens the
+//! bounds on the line's other aquariums and on the other lines of the aquarium, and so on until
+//! nothing more can be ruled out. Each ruled out level remembers the line that ruled it out, so a
+//! failure can still be traced back to the assignments behind it.
+
+use alloc::vec;
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use crate::observer::SolverObserver;
+use crate::stats::SolveStats;
+use crate::{Board, CellState, Deduction, HintMode};
+
+/// Counters for a single search
+#[derive(Default, Copy, Clone, Debug)]
+pub struct SearchStats {
+    /// Number of levels tried
+    pub nodes: usize,
+    /// Number of levels tried for aquariums that had more than one level left, leaving out
+    /// the levels forced on the rest
+    pub guesses: usize,
+    /// Number of levels undone after their subtree failed
+    pub backtracks: usize,
+    /// Number of nogoods recorded
+    pub nogoods: usize,
+    /// Number of levels rejected because they completed a nogood
+    pub nogood_prunes: usize,
+    /// Number of times the search started over from the top, keeping what it had learned
+    pub restarts: usize,
+    /// Whether the observer stopped the search before it finished
+    pub stopped: bool,
+}
+
+/// How the search picks the next aquarium to branch on
+#[derive(PartialEq, Eq, Copy, Clone, Debug, Default)]
+pub enum Heuristic {
+    /// The first undecided aquarium by partition id
+    Index,
+    /// The undecided aquarium with the fewest levels that still fit its lines,
+    /// breaking ties by the most rows touched
+    MostConstrained,
+    /// Like `MostConstrained`, but with the levels that fit counted against how often the
+    /// aquarium's lines have failed (dom/wdeg)
+    #[default]
+    DomWdeg,
+}
+
+impl Heuristic {
+    pub fn from_name(name: &str) -> Option<Heuristic> {
+        match name {
+            "index" => Some(Heuristic::Index),
+            "constrained" => Some(Heuristic::MostConstrained),
+            "dom-wdeg" => Some(Heuristic::DomWdeg),
+            _ => None,
+        }
+    }
+}
+
+/// An aquarium as a search variable
+struct Aquarium {
+    partition: usize,
+    /// Every level the aquarium may take given the cells already decided on the board
+    levels: Vec<Option<usize>>,
+    /// Every line (row iy, or column ix as `height + ix`) the aquarium has cells in
+    lines: Vec<usize>,
+    /// The number of rows among `lines`
+    n_rows: usize,
+    /// For each level, the number of flooded cells it puts in each of `lines`
+    counts: Vec<Vec<isize>>,
+    /// The fewest and most cells the aquarium can flood in each of `lines`,
+    /// over the levels not ruled out
+    min: Vec<isize>,
+    max: Vec<isize>,
+    /// For each level, the line that ruled it out, if any
+    ruled_out: Vec<Option<usize>>,
+    /// The number of levels not ruled out
+    n_open: usize,
+}
+
+/// The aquariums responsible for a failure, by index
+type Conflict = Vec<usize>;
+
+enum Outcome {
+    Solved,
+    Failed(Conflict),
+    Stopped,
+    /// Ran out of levels to try before the next restart
+    Restart,
+}
+
+struct Search {
+    heuristic: Heuristic,
+    aquariums: Vec<Aquarium>,
+    /// The hint for each line
+    hints: Vec<isize>,
+    /// Lines whose hints are left out, so any amount of water will do
+    ignored: Vec<bool>,
+    /// Whether the hints are only lower bounds, so a line can take more water than its hint
+    at_least: bool,
+    /// The aquariums with cells in each line
+    line_aquariums: Vec<Vec<usize>>,
+    /// Flooded cells in each line from assigned aquariums
+    flooded: Vec<isize>,
+    /// Levels ruled out as (aquarium, level), in order, so they can be let back in on backtracking
+    trail: Vec<(usize, usize)>,
+    /// Lines whose bounds changed since they were last propagated, and whether each line is queued
+    queue: Vec<usize>,
+    queued: Vec<bool>,
+    /// For each line, one more than the number of times it has failed
+    weights: Vec<usize>,
+    /// The number of levels to try before starting over
+    node_limit: usize,
+    /// The least and most cells the unassigned aquariums can still flood in each line
+    min_open: Vec<isize>,
+    max_open: Vec<isize>,
+    /// The chosen level of each aquarium, as an index into its `levels`
+    assigned: Vec<Option<usize>>,
+    /// Combinations of (aquarium, level index) known not to lead to a solution
+    nogoods: Vec<Vec<(usize, usize)>>,
+    /// For each aquarium, the nogoods it appears in
+    watches: Vec<Vec<usize>>,
+    /// Pairs of aquariums that are mirror images of each other, when the board is left-right symmetric
+    /// and symmetry breaking is enabled. Only solutions no greater than their mirror image are searched.
+    mirror_pairs: Vec<(usize, usize)>,
+    /// When counting, the number of solutions to stop at. Otherwise the search stops at the first.
+    count_limit: Option<usize>,
+    /// Solutions counted so far, including the mirror images skipped by symmetry breaking
+    solutions: usize,
+    stats: SearchStats,
+}
+
+impl Search {
+    fn new(board: &Board, heuristic: Heuristic) -> Search {
+        let n_lines = board.height + board.width;
+        let mut hints = board.row_hints.clone();
+        hints.extend(&board.col_hints);
+
+        let aquariums: Vec<_> = board
+            .partitions()
+            .into_iter()
+            .map(|partition| Aquarium::new(board, partition))
+            .collect();
+
+        let mut line_aquariums = vec![Vec::new(); n_lines];
+        let mut min_open = vec![0; n_lines];
+        let mut max_open = vec![0; n_lines];
+        for (ia, aquarium) in aquariums.iter().enumerate() {
+            for (k, &line) in aquarium.lines.iter().enumerate() {
+                line_aquariums[line].push(ia);
+                min_open[line] += aquarium.min[k];
+                max_open[line] += aquarium.max[k];
+            }
+        }
+
+        let n_aquariums = aquariums.len();
+        Search {
+            heuristic,
+            aquariums,
+            hints,
+            ignored: vec![false; n_lines],
+            at_least: board.hint_mode == HintMode::AtLeast,
+            line_aquariums,
+            flooded: vec![0; n_lines],
+            trail: Vec::new(),
+            queue: Vec::new(),
+            queued: vec![false; n_lines],
+            weights: vec![1; n_lines],
+            node_limit: usize::MAX,
+            min_open,
+            max_open,
+            assigned: vec![None; n_aquariums],
+            nogoods: Vec::new(),
+            watches: vec![Vec::new(); n_aquariums],
+            mirror_pairs: Vec::new(),
+            count_limit: None,
+            solutions: 0,
+            stats: SearchStats::default(),
+        }
+    }
+
+    /// Check a line against its hint. On failure, the assigned aquariums that caused it,
+    /// directly or by ruling out levels of the line's other aquariums.
+    fn check_line(&self, line: usize) -> Result<(), Conflict> {
+        if self.ignored[line] {
+            return Ok(());
+        }
+        let hint = self.hints[line];
+        let overflow = !self.at_least && self.flooded[line] + self.min_open[line] > hint;
+        let underflow = self.flooded[line] + self.max_open[line] < hint;
+        if !overflow && !underflow {
+            return Ok(());
+        }
+
+        // Too much water only comes from the aquariums that put water in the line,
+        // but too little comes from every assigned aquarium in it
+        Err(self.blame(line, overflow))
+    }
+
+    /// The assigned aquariums behind a line failing: those in the line, or with `only_water` just
+    /// those putting water in it, along with those behind the levels ruled out in the line
+    fn blame(&self, line: usize, only_water: bool) -> Conflict {
+        let mut conflict: Conflict = self.line_aquariums[line]
+            .iter()
+            .copied()
+            .filter(|&ia| match self.assigned[ia] {
+                None => false,
+                Some(level) => !only_water || self.aquariums[ia].count_in(level, line) > 0,
+            })
+            .collect();
+        let reasons = self.line_aquariums[line]
+            .iter()
+            .filter(|&&ia| self.assigned[ia].is_none())
+            .flat_map(|&ia| self.aquariums[ia].reasons());
+        merge(&mut conflict, &self.explain(reasons));
+        conflict
+    }
+
+    /// The assigned aquariums behind the levels ruled out by `lines`: the aquariums assigned in
+    /// those lines, and, through the lines that ruled out levels of the unassigned ones, the
+    /// aquariums behind those
+    fn explain<I: IntoIterator<Item = usize>>(&self, lines: I) -> Conflict {
+        let mut seen = vec![false; self.hints.len()];
+        let mut lines: Vec<_> = lines.into_iter().collect();
+        for &line in &lines {
+            seen[line] = true;
+        }
+        let mut conflict = Vec::new();
+        while let Some(line) = lines.pop() {
+            for &ia in &self.line_aquariums[line] {
+                if self.assigned[ia].is_some() {
+                    merge(&mut conflict, &[ia]);
+                    continue;
+                }
+                for reason in self.aquariums[ia].reasons() {
+                    if !seen[reason] {
+                        seen[reason] = true;
+                        lines.push(reason);
+                    }
+                }
+            }
+        }
+        conflict
+    }
+
+    /// Check the lines before any guess and rule out the levels that can't fit.
+    /// False if the board can't be completed from here.
+    fn start(&mut self) -> bool {
+        let broken = (0..self.hints.len()).any(|line| self.check_line(line).is_err());
+        // An aquarium with no possible level
+        let stuck = self.aquariums.iter().any(|it| it.levels.is_empty());
+        if broken || stuck {
+            return false;
+        }
+        for line in 0..self.hints.len() {
+            self.enqueue(line);
+        }
+        self.propagate().is_ok()
+    }
+
+    fn enqueue(&mut self, line: usize) {
+        if !self.queued[line] {
+            self.queued[line] = true;
+            self.queue.push(line);
+        }
+    }
+
+    /// Rule out the levels of unassigned aquariums that no longer fit the queued lines, queueing the
+    /// lines of every aquarium that loses a level, until the queue is empty.
+    /// On failure, the assigned aquariums responsible; the queue is emptied either way.
+    fn propagate(&mut self) -> Result<(), Conflict> {
+        let result = self.propagate_queue();
+        for line in self.queue.drain(..) {
+            self.queued[line] = false;
+        }
+        result
+    }
+
+    fn propagate_queue(&mut self) -> Result<(), Conflict> {
+        while let Some(line) = self.queue.pop() {
+            self.queued[line] = false;
+            let changed = self
+                .check_line(line)
+                .and_then(|()| self.propagate_line(line));
+            if changed.is_err() {
+                self.weights[line] += 1;
+            }
+            for ia in changed? {
+                self.update_bounds(ia);
+                for k in 0..self.aquariums[ia].lines.len() {
+                    let line = self.aquariums[ia].lines[k];
+                    self.enqueue(line);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Rule out each level of the line's unassigned aquariums that no combination of levels of the
+    /// others can make up the line's hint with. Returns the aquariums that lost a level,
+    /// or on failure the assigned aquariums responsible.
+    ///
+    /// Works through the aquariums in turn, finding the amounts of water each run of them can put
+    /// in the line from the start and from the end; a level is kept if some amount from before it
+    /// and some from after it make up the rest.
+    fn propagate_line(&mut self, line: usize) -> Result<Vec<usize>, Conflict> {
+        if self.ignored[line] {
+            return Ok(Vec::new());
+        }
+        if self.at_least {
+            return Ok(self.propagate_line_at_least(line));
+        }
+        // Unassigned aquariums, with the position of the line among their lines
+        let open: Vec<(usize, usize)> = self.line_aquariums[line]
+            .iter()
+            .filter(|&&ia| self.assigned[ia].is_none())
+            .map(|&ia| {
+                let k = self.aquariums[ia]
+                    .lines
+                    .iter()
+                    .position(|&it| it == line)
+                    .unwrap();
+                (ia, k)
+            })
+            .collect();
+        // The water still needed, which `check_line` has found is within reach
+        let needed = usize::try_from(self.hints[line] - self.flooded[line]).unwrap();
+        let words = (needed + 1).div_ceil(64);
+        let n = open.len();
+
+        // Sets of amounts as bits. Bit x of set i of `before`: whether the first i aquariums
+        // can put in x cells. Bit x of set i of `after`: whether the aquariums from i on
+        // can put in `needed - x`, so a level's amount shifts the two sets the same way.
+        let mut before = vec![0; (n + 1) * words];
+        let mut after = vec![0; (n + 1) * words];
+        before[0] = 1;
+        after[n * words + needed / 64] = 1 << (needed % 64);
+        for (i, &(ia, k)) in open.iter().enumerate() {
+            let (done, rest) = before.split_at_mut((i + 1) * words);
+            for amount in self.aquariums[ia].amounts(k) {
+                or_shifted_up(&mut rest[..words], &done[i * words..], amount);
+            }
+        }
+        for (i, &(ia, k)) in open.iter().enumerate().rev() {
+            let (rest, done) = after.split_at_mut((i + 1) * words);
+            for amount in self.aquariums[ia].amounts(k) {
+                or_shifted_down(&mut rest[i * words..], &done[..words], amount);
+            }
+        }
+        if before[n * words + needed / 64] & 1 << (needed % 64) == 0 {
+            return Err(self.blame(line, false));
+        }
+
+        let mut changed = Vec::new();
+        for (i, &(ia, k)) in open.iter().enumerate() {
+            let before = &before[i * words..(i + 1) * words];
+            let after = &after[(i + 1) * words..(i + 2) * words];
+            let aquarium = &mut self.aquariums[ia];
+            let mut lost = false;
+            for level in 0..aquarium.levels.len() {
+                if aquarium.ruled_out[level].is_some() {
+                    continue;
+                }
+                let amount = usize::try_from(aquarium.counts[level][k]).unwrap();
+                if !meets_shifted_down(before, after, amount) {
+                    aquarium.ruled_out[level] = Some(line);
+                    aquarium.n_open -= 1;
+                    self.trail.push((ia, level));
+                    lost = true;
+                }
+            }
+            if lost {
+                changed.push(ia);
+            }
+        }
+        Ok(changed)
+    }
+
+    /// Like `propagate_line` for a hint that is only a lower bound: rule out each level that
+    /// leaves the line short even with the most water the others can add. `check_line` has
+    /// already found the line can be met, so this can't fail.
+    fn propagate_line_at_least(&mut self, line: usize) -> Vec<usize> {
+        let needed = self.hints[line] - self.flooded[line];
+        let mut changed = Vec::new();
+        for &ia in &self.line_aquariums[line] {
+            if self.assigned[ia].is_some() {
+                continue;
+            }
+            let aquarium = &mut self.aquariums[ia];
+            let k = aquarium.lines.iter().position(|&it| it == line).unwrap();
+            let others = self.max_open[line] - aquarium.max[k];
+            let mut lost = false;
+            for level in 0..aquarium.levels.len() {
+                if aquarium.ruled_out[level].is_none()
+                    && aquarium.counts[level][k] + others < needed
+                {
+                    aquarium.ruled_out[level] = Some(line);
+                    aquarium.n_open -= 1;
+                    self.trail.push((ia, level));
+                    lost = true;
+                }
+            }
+            if lost {
+                changed.push(ia);
+            }
+        }
+        changed
+    }
+
+    /// Recompute the bounds of unassigned aquarium `ia` over the levels not ruled out,
+    /// and the open bounds of its lines with them
+    fn update_bounds(&mut self, ia: usize) {
+        let aquarium = &mut self.aquariums[ia];
+        for (k, &line) in aquarium.lines.iter().enumerate() {
+            let open = (0..aquarium.levels.len())
+                .filter(|&level| aquarium.ruled_out[level].is_none())
+                .map(|level| aquarium.counts[level][k]);
+            let min = open.clone().min().unwrap_or(0);
+            let max = open.max().unwrap_or(0);
+            self.min_open[line] += min - aquarium.min[k];
+            self.max_open[line] += max - aquarium.max[k];
+            aquarium.min[k] = min;
+            aquarium.max[k] = max;
+        }
+    }
+
+    /// Let back in every level ruled out since the trail was `mark` long
+    fn restore(&mut self, mark: usize) {
+        while self.trail.len() > mark {
+            let (ia, level) = self.trail.pop().unwrap();
+            let aquarium = &mut self.aquariums[ia];
+            aquarium.ruled_out[level] = None;
+            aquarium.n_open += 1;
+            // Update once the aquarium's run of entries is restored
+            let run_continues = self.trail.len() > mark && self.trail.last().unwrap().0 == ia;
+            if !run_continues {
+                self.update_bounds(ia);
+            }
+        }
+    }
+
+    /// Give aquarium `ia` the level at `level` in its list of levels,
+    /// failing if that breaks a line or completes a nogood.
+    /// The assignment is kept either way; `unassign` undoes it.
+    fn assign(&mut self, ia: usize, level: usize) -> Result<(), Conflict> {
+        let aquarium = &self.aquariums[ia];
+        for (k, &line) in aquarium.lines.iter().enumerate() {
+            self.flooded[line] += aquarium.counts[level][k];
+            self.min_open[line] -= aquarium.min[k];
+            self.max_open[line] -= aquarium.max[k];
+        }
+        self.assigned[ia] = Some(level);
+
+        for &nogood in &self.watches[ia] {
+            let hit = self.nogoods[nogood]
+                .iter()
+                .all(|&(ib, level)| self.assigned[ib] == Some(level));
+            if hit {
+                self.stats.nogood_prunes += 1;
+                return Err(self.nogoods[nogood].iter().map(|&(ib, _)| ib).collect());
+            }
+        }
+
+        for &line in &self.aquariums[ia].lines {
+            self.check_line(line)?;
+        }
+        self.check_mirror()
+    }
+
+    /// Check that the levels so far are no greater than their mirror image,
+    /// comparing mirror pairs in order. On failure, the aquariums compared.
+    fn check_mirror(&self) -> Result<(), Conflict> {
+        let mut compared = Vec::new();
+        for &(ia, ib) in &self.mirror_pairs {
+            let (level_a, level_b) = match (self.assigned[ia], self.assigned[ib]) {
+                (Some(a), Some(b)) => (self.aquariums[ia].levels[a], self.aquariums[ib].levels[b]),
+                _ => return Ok(()),
+            };
+            compared.push(ia);
+            compared.push(ib);
+            if level_a < level_b {
+                return Ok(());
+            }
+            if level_a > level_b {
+                return Err(compared);
+            }
+        }
+        Ok(())
+    }
+
+    /// The number of solutions the current, complete, assignment stands for
+    fn solution_weight(&self) -> usize {
+        let level = |ia: usize| self.aquariums[ia].levels[self.assigned[ia].unwrap()];
+        let own_mirror = self
+            .mirror_pairs
+            .iter()
+            .all(|&(ia, ib)| level(ia) == level(ib));
+        if own_mirror {
+            1
+        } else {
+            2
+        }
+    }
+
+    /// Find the aquariums that mirror each other, if the board is left-right symmetric
+    fn find_mirror_pairs(&mut self, board: &Board) {
+        if board.col_hints.iter().ne(board.col_hints.iter().rev()) {
+            return;
+        }
+        let mut mirror = vec![None; self.aquariums.len()];
+        for iy in 0..board.height {
+            for ix in 0..board.width {
+                let cell = board.cell_at(ix, iy);
+                let image = board.cell_at(board.width - 1 - ix, iy);
+                if cell.state != image.state {
+                    return;
+                }
+                // Aquariums are built in partition order, so a partition is its aquarium's index
+                let (ia, ib) = (cell.partition, image.partition);
+                match mirror[ia] {
+                    None => mirror[ia] = Some(ib),
+                    Some(it) if it == ib => {}
+                    Some(_) => return,
+                }
+            }
+        }
+
+        self.mirror_pairs = mirror
+            .iter()
+            .enumerate()
+            .filter_map(|(ia, &ib)| ib.filter(|&ib| ia < ib).map(|ib| (ia, ib)))
+            .collect();
+    }
+
+    /// The aquariums that currently have a level
+    fn assigned_aquariums(&self) -> Vec<usize> {
+        (0..self.aquariums.len())
+            .filter(|&ia| self.assigned[ia].is_some())
+            .collect()
+    }
+
+    fn unassign(&mut self, ia: usize) {
+        let level = self.assigned[ia].take().unwrap();
+        let aquarium = &self.aquariums[ia];
+        for (k, &line) in aquarium.lines.iter().enumerate() {
+            self.flooded[line] -= aquarium.counts[level][k];
+            self.min_open[line] += aquarium.min[k];
+            self.max_open[line] += aquarium.max[k];
+        }
+    }
+
+    /// Remember that the current levels of the aquariums in `conflict` can't all hold at once
+    fn record_nogood(&mut self, conflict: &[usize]) {
+        if conflict.is_empty() {
+            return;
+        }
+        let nogood: Vec<_> = conflict
+            .iter()
+            .map(|&ia| (ia, self.assigned[ia].unwrap()))
+            .collect();
+        let id = self.nogoods.len();
+        for &(ia, _) in &nogood {
+            self.watches[ia].push(id);
+        }
+        self.nogoods.push(nogood);
+        self.stats.nogoods += 1;
+    }
+
+    /// The next aquarium to branch on
+    fn next_aquarium(&self) -> Option<usize> {
+        let open = (0..self.aquariums.len()).filter(|&ia| self.assigned[ia].is_none());
+        match self.heuristic {
+            Heuristic::Index => open.min(),
+            Heuristic::MostConstrained => open.min_by_key(|&ia| {
+                let aquarium = &self.aquariums[ia];
+                (aquarium.n_open, core::cmp::Reverse(aquarium.n_rows))
+            }),
+            Heuristic::DomWdeg => open.min_by(|&ia, &ib| {
+                let (a, b) = (&self.aquariums[ia], &self.aquariums[ib]);
+                // Compare n_open / weight without dividing
+                (a.n_open * self.weight(ib))
+                    .cmp(&(b.n_open * self.weight(ia)))
+                    .then(b.n_rows.cmp(&a.n_rows))
+            }),
+        }
+    }
+
+    /// The failures of the aquarium's lines
+    fn weight(&self, ia: usize) -> usize {
+        self.aquariums[ia]
+            .lines
+            .iter()
+            .map(|&line| self.weights[line])
+            .sum()
+    }
+
+    /// The levels of aquarium `ia` not ruled out, those leaving the most room in its tightest
+    /// lines first
+    fn level_order(&self, ia: usize) -> Vec<usize> {
+        let aquarium = &self.aquariums[ia];
+        let mut order: Vec<_> = (0..aquarium.levels.len())
+            .filter(|&level| aquarium.ruled_out[level].is_none())
+            .collect();
+        order.sort_by_key(|&level| {
+            let room: isize = (0..aquarium.lines.len())
+                .filter(|&k| !self.ignored[aquarium.lines[k]])
+                .map(|k| {
+                    let line = aquarium.lines[k];
+                    let flooded = self.flooded[line] + aquarium.counts[level][k];
+                    let min = flooded + self.min_open[line] - aquarium.min[k];
+                    let max = flooded + self.max_open[line] - aquarium.max[k];
+                    (self.hints[line] - min).min(max - self.hints[line])
+                })
+                .sum();
+            core::cmp::Reverse(room)
+        });
+        order
+    }
+
+    /// Search from the start, restarting as needed, until it succeeds, fails or is stopped
+    fn run(&mut self, observer: &mut dyn SolverObserver) -> Outcome {
+        // A bad early guess can take a long time to fail, so start over now and then with a
+        // growing budget of levels, keeping the nogoods and line weights learned so far
+        let mut budget = 100;
+        loop {
+            self.node_limit = self.stats.nodes + budget;
+            match self.search(observer) {
+                Outcome::Restart => {
+                    self.stats.restarts += 1;
+                    budget = budget * 3 / 2;
+                }
+                outcome => return outcome,
+            }
+        }
+    }
+
+    fn search(&mut self, observer: &mut dyn SolverObserver) -> Outcome {
+        let ia = match self.next_aquarium() {
+            Some(ia) => ia,
+            None => match self.count_limit {
+                Some(limit) => {
+                    self.solutions += self.solution_weight();
+                    if self.solutions >= limit {
+                        return Outcome::Solved;
+                    }
+                    // Keep looking, without blaming any one aquarium
+                    return Outcome::Failed(self.assigned_aquariums());
+                }
+                None => return Outcome::Solved,
+            },
+        };
+
+        let solutions_before = self.solutions;
+        let mut conflict = Vec::new();
+        let partition = self.aquariums[ia].partition;
+        let order = self.level_order(ia);
+        let forced = order.len() == 1;
+        for level in order {
+            if self.stats.nodes >= self.node_limit {
+                return Outcome::Restart;
+            }
+            self.stats.nodes += 1;
+            if !forced {
+                self.stats.guesses += 1;
+            }
+            let water_level = self.aquariums[ia].levels[level];
+            if !observer.on_guess(partition, water_level) {
+                self.stats.stopped = true;
+                return Outcome::Stopped;
+            }
+            let mark = self.trail.len();
+            let assigned = self.assign(ia, level).and_then(|()| {
+                for k in 0..self.aquariums[ia].lines.len() {
+                    let line = self.aquariums[ia].lines[k];
+                    self.enqueue(line);
+                }
+                self.propagate()
+            });
+            if let Err(cause) = assigned {
+                self.restore(mark);
+                self.unassign(ia);
+                observer.on_backtrack(partition, water_level);
+                merge(&mut conflict, &cause);
+                continue;
+            }
+
+            match self.search(observer) {
+                Outcome::Solved => return Outcome::Solved,
+                Outcome::Stopped => return Outcome::Stopped,
+                Outcome::Restart => {
+                    self.restore(mark);
+                    self.unassign(ia);
+                    observer.on_backtrack(partition, water_level);
+                    return Outcome::Restart;
+                }
+                Outcome::Failed(cause) => {
+                    self.restore(mark);
+                    self.unassign(ia);
+                    observer.on_backtrack(partition, water_level);
+                    self.stats.backtracks += 1;
+                    if !cause.contains(&ia) {
+                        // Nothing about this aquarium caused the failure,
+                        // so none of its other levels can fix it
+                        return Outcome::Failed(cause);
+                    }
+                    merge(&mut conflict, &cause);
+                }
+            }
+        }
+
+        if self.solutions > solutions_before {
+            // Not a failure, so there is nothing to learn
+            return Outcome::Failed(self.assigned_aquariums());
+        }
+        // The levels ruled out before this aquarium was picked failed too
+        merge(&mut conflict, &self.explain(self.aquariums[ia].reasons()));
+        conflict.retain(|&ib| ib != ia);
+        self.record_nogood(&conflict);
+        Outcome::Failed(conflict)
+    }
+}
+
+impl Aquarium {
+    fn new(board: &Board, partition: usize) -> Aquarium {
+        let cells: Vec<_> = (0..board.height)
+            .flat_map(|iy| (0..board.width).map(move |ix| (ix, iy)))
+            .filter(|&(ix, iy)| board.partition_at(ix, iy) == partition)
+            .collect();
+
+        let mut rows: Vec<usize> = cells.iter().map(|&(_, iy)| iy).collect();
+        rows.dedup();
+
+        // The level has to keep every flooded cell under water and every invalid cell dry
+        let allowed = |level: Option<usize>| {
+            cells
+                .iter()
+                .all(|&(ix, iy)| match board.cell_state_at(ix, iy) {
+                    CellState::Empty => true,
+                    CellState::Flooded => matches!(level, Some(level) if iy >= level),
+                    CellState::Invalid => !matches!(level, Some(level) if iy >= level),
+                })
+        };
+        let levels: Vec<_> = core::iter::once(None)
+            .chain(rows.into_iter().map(Some))
+            .filter(|&level| allowed(level))
+            .collect();
+
+        let mut lines: Vec<usize> = cells.iter().map(|&(_, iy)| iy).collect();
+        lines.extend(cells.iter().map(|&(ix, _)| board.height + ix));
+        lines.sort_unstable();
+        lines.dedup();
+
+        let counts: Vec<Vec<isize>> = levels
+            .iter()
+            .map(|&level| {
+                lines
+                    .iter()
+                    .map(|&line| {
+                        let in_line = |&&(ix, iy): &&(usize, usize)| {
+                            if line < board.height {
+                                iy == line
+                            } else {
+                                board.height + ix == line
+                            }
+                        };
+                        let flooded =
+                            |&&(_, iy): &&(usize, usize)| matches!(level, Some(level) if iy >= level);
+                        isize::try_from(cells.iter().filter(in_line).filter(flooded).count())
+                            .unwrap()
+                    })
+                    .collect()
+            })
+            .collect();
+
+        let n_rows = lines.iter().filter(|&&line| line < board.height).count();
+
+        let min = (0..lines.len())
+            .map(|k| counts.iter().map(|count| count[k]).min().unwrap_or(0))
+            .collect();
+        let max = (0..lines.len())
+            .map(|k| counts.iter().map(|count| count[k]).max().unwrap_or(0))
+            .collect();
+
+        let n_open = levels.len();
+        Aquarium {
+            partition,
+            ruled_out: vec![None; n_open],
+            n_open,
+            levels,
+            lines,
+            n_rows,
+            counts,
+            min,
+            max,
+        }
+    }
+
+    /// The cells of water each level not ruled out puts in the line at `k` in `lines`
+    fn amounts(&self, k: usize) -> impl Iterator<Item = usize> + '_ {
+        (0..self.levels.len())
+            .filter(move |&level| self.ruled_out[level].is_none())
+            .map(move |level| usize::try_from(self.counts[level][k]).unwrap())
+    }
+
+    /// The lines that ruled out any of the aquarium's levels
+    fn reasons(&self) -> impl Iterator<Item = usize> + '_ {
+        self.ruled_out.iter().flatten().copied()
+    }
+
+    /// The number of cells flooded in `line` at the level with index `level`
+    fn count_in(&self, level: usize, line: usize) -> isize {
+        match self.lines.iter().position(|&it| it == line) {
+            Some(k) => self.counts[level][k],
+            None => 0,
+        }
+    }
+}
+
+/// Add the aquariums in `other` to `conflict`, keeping it free of duplicates
+fn merge(conflict: &mut Conflict, other: &[usize]) {
+    for &ia in other {
+        if !conflict.contains(&ia) {
+            conflict.push(ia);
+        }
+    }
+}
+
+/// Search for levels completing the board from its current state, using the default heuristic.
+/// On success the board is filled in and true is returned; otherwise the board is left as it was.
+pub fn search(board: &mut Board) -> (bool, SearchStats) {
+    search_with(board, Heuristic::default())
+}
+
+/// `search`, picking aquariums to branch on with the given heuristic
+pub fn search_with(board: &mut Board, heuristic: Heuristic) -> (bool, SearchStats) {
+    search_observed(board, heuristic, &mut ())
+}
+
+/// `search_with`, telling `observer` about every guess and backtrack
+pub fn search_observed(
+    board: &mut Board,
+    heuristic: Heuristic,
+    observer: &mut dyn SolverObserver,
+) -> (bool, SearchStats) {
+    let mut search = Search::new(board, heuristic);
+    if !search.start() {
+        return (false, search.stats);
+    }
+    match search.run(observer) {
+        Outcome::Solved => {
+            for (aquarium, level) in search.aquariums.iter().zip(&search.assigned) {
+                let level = aquarium.levels[level.unwrap()];
+                board
+                    .set_water_level(aquarium.partition, level)
+                    .expect("Search produced a level the board rejects");
+            }
+            (true, search.stats)
+        }
+        Outcome::Failed(_) | Outcome::Stopped | Outcome::Restart => (false, search.stats),
+    }
+}
+
+/// Whether the board can be completed from its current state when the hints of the `ignored`
+/// lines are left out. Lines are the rows, then the columns.
+pub fn has_solution_ignoring(board: &Board, ignored: &[bool]) -> bool {
+    let mut search = Search::new(board, Heuristic::default());
+    search.ignored.copy_from_slice(ignored);
+    search.start() && matches!(search.run(&mut ()), Outcome::Solved)
+}
+
+/// Count the solutions of the board from its current state, stopping once `limit` have been found.
+/// With `symmetry` set, a left-right symmetric board only has the solutions no greater than their
+/// mirror image searched, and each asymmetric one is counted twice.
+pub fn count_solutions(board: &Board, limit: usize, symmetry: bool) -> (usize, SearchStats) {
+    let mut search = Search::new(board, Heuristic::default());
+    search.count_limit = Some(limit);
+    if symmetry {
+        search.find_mirror_pairs(board);
+    }
+
+    if !search.start() {
+        return (0, search.stats);
+    }
+
+    search.search(&mut ());
+    (search.solutions, search.stats)
+}
+
+/// Whether the board has exactly one solution from its current state
+pub fn is_unique(board: &Board) -> bool {
+    count_solutions(board, 2, true).0 == 1
+}
+
+/// Run the rules, then search for anything they leave undecided
+pub fn solve(board: &mut Board) -> SolveStats {
+    solve_observed(board, &mut ())
+}
+
+/// `solve`, telling `observer` about every step of both the rules and the search.
+/// If the observer stops the rules, the search doesn't start.
+pub fn solve_observed(board: &mut Board, observer: &mut dyn SolverObserver) -> SolveStats {
+    let mut stats = board.solve_observed(&mut Vec::new(), observer);
+    if !board.is_solved() && !stats.stopped {
+        #[cfg(feature = "std")]
+        let start = std::time::Instant::now();
+        let (_, search_stats) = search_observed(board, Heuristic::default(), observer);
+        stats.guesses = search_stats.guesses;
+        stats.stopped = search_stats.stopped;
+        #[cfg(feature = "std")]
+        {
+            stats.elapsed += start.elapsed();
+        }
+    }
+    stats
+}
+
+/// `solve`, giving up between deductions or guesses once `cancelled` is set, e.g. by another thread.
+/// The stats say whether it gave up.
+pub fn solve_cancellable(board: &mut Board, cancelled: &AtomicBool) -> SolveStats {
+    solve_observed(board, &mut Cancellation(cancelled))
+}
+
+/// Stops the solve once the flag it watches is set
+pub(crate) struct Cancellation<'a>(pub(crate) &'a AtomicBool);
+
+impl SolverObserver for Cancellation<'_> {
+    fn on_deduction(&mut self, _board: &Board, _deduction: &Deduction) -> bool {
+        !self.0.load(Ordering::Relaxed)
+    }
+
+    fn on_guess(&mut self, _partition: usize, _level: Option<usize>) -> bool {
+        !self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// `dest |= src << by`, for sets of bits held in words, lowest first
+fn or_shifted_up(dest: &mut [u64], src: &[u64], by: usize) {
+    let (words, bits) = (by / 64, by % 64);
+    for (i, dest) in dest.iter_mut().enumerate().skip(words) {
+        let j = i - words;
+        let mut word = src[j] << bits;
+        if bits > 0 && j > 0 {
+            word |= src[j - 1] >> (64 - bits);
+        }
+        *dest |= word;
+    }
+}
+
+/// `dest |= src >> by`, for sets of bits held in words, lowest first
+fn or_shifted_down(dest: &mut [u64], src: &[u64], by: usize) {
+    let (words, bits) = (by / 64, by % 64);
+    for i in 0..dest.len().saturating_sub(words) {
+        let j = i + words;
+        let mut word = src[j] >> bits;
+        if bits > 0 && j + 1 < src.len() {
+            word |= src[j + 1] << (64 - bits);
+        }
+        dest[i] |= word;
+    }
+}
+
+/// Whether `a` and `b >> by` have a bit in common
+fn meets_shifted_down(a: &[u64], b: &[u64], by: usize) -> bool {
+    let (words, bits) = (by / 64, by % 64);
+    (0..a.len().saturating_sub(words)).any(|i| {
+        let j = i + words;
+        let mut word = b[j] >> bits;
+        if bits > 0 && j + 1 < b.len() {
+            word |= b[j + 1] << (64 - bits);
+        }
+        a[i] & word != 0
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generate::{self, Rng};
+
+    /// The rows aquarium `partition` has cells in, top down
+    fn rows(board: &Board, partition: usize) -> Vec<usize> {
+        (0..board.height)
+            .filter(|&iy| (0..board.width).any(|ix| board.partition_at(ix, iy) == partition))
+            .collect()
+    }
+
+    /// Count the solutions by trying every level of every aquarium
+    fn brute_force(board: &Board) -> usize {
+        fn count(board: &Board, partition: usize) -> usize {
+            if partition == board.n_partitions {
+                return board.is_solved() as usize;
+            }
+            let levels = rows(board, partition).into_iter().map(Some);
+            std::iter::once(None)
+                .chain(levels)
+                .map(|level| {
+                    let mut board = board.clone();
+                    match board.set_water_level(partition, level) {
+                        Ok(()) => count(&board, partition + 1),
+                        Err(_) => 0,
+                    }
+                })
+                .sum()
+        }
+        count(board, 0)
+    }
+
+    /// A random left-right symmetric puzzle, with hints that read the same both ways so the search
+    /// can break the symmetry
+    fn symmetric_puzzle(width: usize, height: usize, rng: &mut Rng) -> Board {
+        let half = width.div_ceil(2);
+        // Join random neighbours of the left half into aquariums
+        let mut parent: Vec<_> = (0..half * height).collect();
+        fn root(parent: &mut [usize], i: usize) -> usize {
+            if parent[i] != i {
+                parent[i] = root(parent, parent[i]);
+            }
+            parent[i]
+        }
+        for _ in 0..half * height / 2 {
+            let i = rng.below(half * height);
+            let right = i % half + 1 < half;
+            let j = if right && rng.below(2) == 0 {
+                i + 1
+            } else {
+                i + half
+            };
+            if j < half * height {
+                let (a, b) = (root(&mut parent, i), root(&mut parent, j));
+                parent[a] = b;
+            }
+        }
+        // Aquariums at the middle carry on into their mirror image; the rest get their own
+        let middle: Vec<_> = (0..height)
+            .map(|iy| root(&mut parent, iy * half + half - 1))
+            .collect();
+        let mut board = Board::make(width, height);
+        for iy in 0..height {
+            for ix in 0..width {
+                let left = ix.min(width - 1 - ix);
+                let id = root(&mut parent, iy * half + left);
+                let mirrored = ix >= half && !middle.contains(&id);
+                board.cells[iy * width + ix].partition =
+                    id + if mirrored { half * height } else { 0 };
+            }
+        }
+        board.index_partitions();
+
+        // Fill the aquariums at random until the column hints read the same both ways, so the
+        // solution needn't be its own mirror image
+        loop {
+            board.clear();
+            for partition in 0..board.n_partitions {
+                let rows = rows(&board, partition);
+                let level = rows.get(rng.below(rows.len() + 1)).copied();
+                board.set_water_level(partition, level).unwrap();
+            }
+            let flooded = |ix: usize| board.flooded_cols.count(ix);
+            if (0..width).all(|ix| flooded(ix) == flooded(width - 1 - ix)) {
+                break;
+            }
+        }
+        set_hints(&mut board);
+        board
+    }
+
+    /// Take the hints from the cells, and clear them
+    fn set_hints(board: &mut Board) {
+        let flooded = |ix, iy| (board.cell_state_at(ix, iy) == CellState::Flooded) as isize;
+        let row_hints = (0..board.height)
+            .map(|iy| (0..board.width).map(|ix| flooded(ix, iy)).sum())
+            .collect();
+        let col_hints = (0..board.width)
+            .map(|ix| (0..board.height).map(|iy| flooded(ix, iy)).sum())
+            .collect();
+        board.row_hints = row_hints;
+        board.col_hints = col_hints;
+        board.clear();
+    }
+
+    #[test]
+    fn symmetry_breaking_counts_every_solution() {
+        let mut rng = Rng::new(7);
+        let mut paired = 0;
+        for (width, height) in [(2, 2), (3, 3), (4, 3), (3, 4), (4, 4), (5, 3)] {
+            for _ in 0..15 {
+                let board = symmetric_puzzle(width, height, &mut rng);
+                let expected = brute_force(&board);
+                assert!(expected > 0);
+                assert_eq!(count_solutions(&board, usize::MAX, true).0, expected);
+                assert_eq!(count_solutions(&board, usize::MAX, false).0, expected);
+                let mut search = Search::new(&board, Heuristic::default());
+                search.find_mirror_pairs(&board);
+                paired += (expected > 1 && !search.mirror_pairs.is_empty()) as usize;
+            }
+        }
+        // Enough boards pair up aquariums and have several solutions for the symmetry to be tested
+        assert!(paired > 15);
+    }
+
+    #[test]
+    fn solutions_of_asymmetric_boards_are_counted_once() {
+        let mut rng = Rng::new(11);
+        for (width, height) in [(3, 3), (4, 3), (4, 4)] {
+            for _ in 0..15 {
+                let mut board = generate::generate(width, height, &mut rng);
+                set_hints(&mut board);
+                let expected = brute_force(&board);
+                assert_eq!(count_solutions(&board, usize::MAX, true).0, expected);
+                assert_eq!(count_solutions(&board, usize::MAX, false).0, expected);
+            }
+        }
+    }
+
+    #[test]
+    fn forced_levels_are_not_guesses() {
+        // Every aquarium of a solved board has one level left
+        let mut board = Board::make_b0_solved();
+        let (_, stats) = search(&mut board);
+        assert_eq!(stats.guesses, 0);
+        assert_eq!(stats.nodes, board.n_partitions);
+
+        // Harder boards need some real guesses, alongside levels the earlier ones force
+        let mut rng = Rng::new(3);
+        let mut guessed = 0;
+        for _ in 0..10 {
+            let mut board = generate::generate(10, 10, &mut rng);
+            set_hints(&mut board);
+            let (solved, stats) = search(&mut board);
+            assert!(solved);
+            assert!(stats.guesses < stats.nodes);
+            guessed += (stats.guesses > 0) as usize;
+        }
+        assert!(guessed > 0);
+    }
+}
@@ -0,0 +1,122 @@
+//! Per-puzzle solve statistics and, with `std`, the batch runner that collects them.
+
+use core::time::Duration;
+#[cfg(feature = "std")]
+use std::io::{self, Write};
+
+#[cfg(feature = "std")]
+use crate::search;
+#[cfg(feature = "std")]
+use crate::Board;
+
+/// Counters recorded by a single call to `Board::solve`
+#[derive(Default, Copy, Clone, Debug)]
+pub struct SolveStats {
+    /// Number of passes over all of the rules
+    pub iterations: usize,
+    /// Number of rule applications that changed the board
+    pub deductions: usize,
+    /// Number of water levels the search guessed once the rules stall, leaving out those it
+    /// was left no choice of
+    pub guesses: usize,
+    /// Wall-clock time spent in `solve`, left at zero without `std`
+    pub elapsed: Duration,
+    /// Whether the solve was stopped before it finished
+    pub stopped: bool,
+    /// Whether the rules hit the iteration cap without settling
+    pub stagnated: bool,
+}
+
+/// Where a puzzle came from. A field is empty when the puzzle doesn't say.
+#[cfg(feature = "std")]
+#[derive(Default, Clone, Debug)]
+pub struct Metadata {
+    /// The site or publication, e.g. `puzzle-aquarium.com`
+    pub source: String,
+    /// The source's own id for the puzzle
+    pub id: String,
+    pub author: String,
+    /// When the source published the puzzle, as written there
+    pub date: String,
+}
+
+/// A named puzzle queued for a batch run
+#[cfg(feature = "std")]
+pub struct BatchEntry {
+    pub name: String,
+    /// Declared difficulty, as given by the puzzle source
+    pub difficulty: String,
+    pub metadata: Metadata,
+    pub board: Board,
+}
+
+/// The outcome of solving one `BatchEntry`
+#[cfg(feature = "std")]
+pub struct PuzzleReport {
+    pub name: String,
+    pub difficulty: String,
+    pub metadata: Metadata,
+    pub width: usize,
+    pub height: usize,
+    pub solved: bool,
+    pub stats: SolveStats,
+}
+
+/// Solve each puzzle in turn and record how it went
+#[cfg(feature = "std")]
+pub fn run_batch(entries: Vec<BatchEntry>) -> Vec<PuzzleReport> {
+    entries
+        .into_iter()
+        .map(|mut entry| {
+            let stats = search::solve(&mut entry.board);
+            PuzzleReport {
+                name: entry.name,
+                difficulty: entry.difficulty,
+                metadata: entry.metadata,
+                width: entry.board.width,
+                height: entry.board.height,
+                solved: entry.board.is_solved(),
+                stats,
+            }
+        })
+        .collect()
+}
+
+/// Write one CSV row per report, preceded by a header row
+#[cfg(feature = "std")]
+pub fn write_csv<W: Write>(mut out: W, reports: &[PuzzleReport]) -> io::Result<()> {
+    writeln!(
+        out,
+        "name,difficulty,source,id,author,date,width,height,solved,time_us,iterations,deductions,guesses"
+    )?;
+    for report in reports {
+        writeln!(
+            out,
+            "{},{},{},{},{},{},{},{},{},{},{},{},{}",
+            csv_field(&report.name),
+            csv_field(&report.difficulty),
+            csv_field(&report.metadata.source),
+            csv_field(&report.metadata.id),
+            csv_field(&report.metadata.author),
+            csv_field(&report.metadata.date),
+            report.width,
+            report.height,
+            report.solved,
+            report.stats.elapsed.as_micros(),
+            report.stats.iterations,
+            report.stats.deductions,
+            report.stats.guesses,
+        )?;
+    }
+    Ok(())
+}
+
+/// Quote a field if it would otherwise break the row
+#[cfg(feature = "std")]
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
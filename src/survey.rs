@@ -0,0 +1,150 @@
+//! How much of a corpus the rules solve on their own, and which puzzles need the search.
+
+use std::io::{self, Write};
+use std::path::Path;
+
+use crate::campaign;
+use crate::format;
+use crate::search;
+use crate::CellState;
+
+/// What it took to solve one puzzle
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub enum Need {
+    /// The rules solved it alone
+    Rules,
+    /// The rules stalled and the search finished it
+    Search,
+    /// There is no solution
+    Unsolvable,
+}
+
+pub struct Entry {
+    /// The file's path, followed by `#n` for the nth puzzle of a file holding several
+    pub id: String,
+    pub difficulty: String,
+    pub size: (usize, usize),
+    pub need: Need,
+    /// The cells the rules left undecided
+    pub undecided: usize,
+}
+
+/// Solve every puzzle in the file at `path`, or under it if it's a directory, with the rules,
+/// then with the search if they stall. The files are read a puzzle at a time.
+/// Files and puzzles that can't be read are reported on stderr and left out.
+pub fn run(path: &Path) -> io::Result<Vec<Entry>> {
+    let mut entries = Vec::new();
+    for path in campaign::puzzle_files(path)? {
+        let puzzles = match format::stream_file(&path) {
+            Ok(puzzles) => puzzles,
+            Err(error) => {
+                eprintln!("Skipping {}: {}", path.display(), error);
+                continue;
+            }
+        };
+        for streamed in puzzles {
+            let id = streamed.id(&path);
+            let mut puzzle = match streamed.entry {
+                Ok(puzzle) => puzzle,
+                Err(error) => {
+                    eprintln!("Skipping {}: {}", id, error);
+                    continue;
+                }
+            };
+            let board = &mut puzzle.board;
+            board.solve();
+            let undecided = board
+                .cells
+                .iter()
+                .filter(|cell| cell.state == CellState::Empty)
+                .count();
+            let need = if board.is_solved() {
+                Need::Rules
+            } else if search::search(board).0 {
+                Need::Search
+            } else {
+                Need::Unsolvable
+            };
+            entries.push(Entry {
+                id,
+                difficulty: puzzle.difficulty,
+                size: (board.width, board.height),
+                need,
+                undecided,
+            });
+        }
+    }
+    Ok(entries)
+}
+
+/// Write how many puzzles needed the search, by size and by declared difficulty,
+/// followed by the puzzles that did
+pub fn write_report<W: Write>(mut out: W, entries: &[Entry]) -> io::Result<()> {
+    let mut by_size = group(entries, |entry| {
+        format!("{}x{}", entry.size.0, entry.size.1)
+    });
+    by_size.sort_by_key(|(_, members)| members[0].size);
+    let by_difficulty = group(entries, |entry| match entry.difficulty.as_str() {
+        "" => "-".to_string(),
+        difficulty => difficulty.to_string(),
+    });
+    let all = group(entries, |_| "all".to_string());
+    for (title, groups) in [("size", by_size), ("difficulty", by_difficulty), ("", all)] {
+        writeln!(
+            out,
+            "{:<12} {:>7} {:>7} {:>7} {:>10} {:>9}",
+            title, "puzzles", "rules", "search", "no answer", "% search"
+        )?;
+        for (key, members) in groups {
+            let count = |need| members.iter().filter(|entry| entry.need == need).count();
+            let solvable = members.len() - count(Need::Unsolvable);
+            let share = if solvable == 0 {
+                0.0
+            } else {
+                100.0 * count(Need::Search) as f64 / solvable as f64
+            };
+            writeln!(
+                out,
+                "{:<12} {:>7} {:>7} {:>7} {:>10} {:>8.1}%",
+                key,
+                members.len(),
+                count(Need::Rules),
+                count(Need::Search),
+                count(Need::Unsolvable),
+                share
+            )?;
+        }
+        writeln!(out)?;
+    }
+
+    writeln!(out, "Needing the search:")?;
+    for entry in entries.iter().filter(|entry| entry.need == Need::Search) {
+        writeln!(
+            out,
+            "  {} ({}x{}, {}): {} cells left by the rules",
+            entry.id,
+            entry.size.0,
+            entry.size.1,
+            if entry.difficulty.is_empty() {
+                "-"
+            } else {
+                &entry.difficulty
+            },
+            entry.undecided
+        )?;
+    }
+    Ok(())
+}
+
+/// The entries grouped by key, in the order the keys first appear
+fn group<F: Fn(&Entry) -> String>(entries: &[Entry], key: F) -> Vec<(String, Vec<&Entry>)> {
+    let mut groups: Vec<(String, Vec<&Entry>)> = Vec::new();
+    for entry in entries {
+        let key = key(entry);
+        match groups.iter_mut().find(|(it, _)| *it == key) {
+            Some((_, members)) => members.push(entry),
+            None => groups.push((key, vec![entry])),
+        }
+    }
+    groups
+}
@@ -0,0 +1,253 @@
+//! SVG drawings of a board, and a solve as a numbered sequence of them for slides and animations.
+//!
+//! A drawing shows the hints, the water and the dry cells, thin lines between the cells and thick
+//! ones over the aquarium walls, like the PDF. Under the board is a caption, and the cells that
+//! changed since the frame before are outlined.
+
+use std::fmt::Write;
+
+use crate::observer::SolverObserver;
+use crate::{search, Board, CellState, Deduction};
+
+/// Side of a cell, in pixels
+const CELL: usize = 40;
+const WATER: &str = "#9cf";
+const DRY: &str = "#eee";
+const CHANGED: &str = "#d33";
+
+/// When to draw a frame
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub enum Step {
+    /// After every deduction
+    Deduction,
+    /// After every pass over the rules
+    Iteration,
+}
+
+impl Step {
+    pub fn from_name(name: &str) -> Option<Step> {
+        match name {
+            "deduction" => Some(Step::Deduction),
+            "iteration" => Some(Step::Iteration),
+            _ => None,
+        }
+    }
+}
+
+/// The board as an SVG drawing captioned `caption`, with the cells `changed` marks outlined
+pub fn render(board: &Board, caption: &str, changed: &[bool]) -> String {
+    // A cell's worth of room above and to the left of the grid for the hints, and below for the
+    // caption
+    let width = CELL * (board.width + 2);
+    let height = CELL * (board.height + 3);
+    let x = |ix: usize| CELL * (ix + 1);
+    let y = |iy: usize| CELL * (iy + 1);
+
+    let mut out = String::new();
+    writeln!(
+        out,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" \
+         viewBox=\"0 0 {} {}\" font-family=\"sans-serif\" text-anchor=\"middle\">",
+        width, height, width, height
+    )
+    .unwrap();
+    writeln!(
+        out,
+        "<rect width=\"{}\" height=\"{}\" fill=\"white\"/>",
+        width, height
+    )
+    .unwrap();
+
+    let font = CELL / 2;
+    for (ix, hint) in board.col_hints.iter().enumerate() {
+        text(
+            &mut out,
+            &hint.to_string(),
+            x(ix) + CELL / 2,
+            CELL * 3 / 4,
+            font,
+        );
+    }
+    for (iy, hint) in board.row_hints.iter().enumerate() {
+        text(
+            &mut out,
+            &hint.to_string(),
+            CELL / 2,
+            y(iy) + CELL * 2 / 3,
+            font,
+        );
+    }
+
+    for iy in 0..board.height {
+        for ix in 0..board.width {
+            let fill = match board.cell_state_at(ix, iy) {
+                CellState::Empty => continue,
+                CellState::Flooded => WATER,
+                CellState::Invalid => DRY,
+            };
+            writeln!(
+                out,
+                "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\"/>",
+                x(ix),
+                y(iy),
+                CELL,
+                CELL,
+                fill
+            )
+            .unwrap();
+        }
+    }
+
+    // Light lines between every cell, then heavy ones over the aquarium walls and the edge
+    let mut thin = String::new();
+    let mut thick = String::new();
+    for iy in 0..board.height {
+        for ix in 0..board.width - 1 {
+            let path = if board.wall_at(ix, iy) {
+                &mut thick
+            } else {
+                &mut thin
+            };
+            write!(path, "M{} {}V{}", x(ix + 1), y(iy), y(iy + 1)).unwrap();
+        }
+    }
+    for iy in 0..board.height - 1 {
+        for ix in 0..board.width {
+            let path = if board.floor_at(ix, iy) {
+                &mut thick
+            } else {
+                &mut thin
+            };
+            write!(path, "M{} {}H{}", x(ix), y(iy + 1), x(ix + 1)).unwrap();
+        }
+    }
+    write!(
+        thick,
+        "M{} {}H{}V{}H{}Z",
+        x(0),
+        y(0),
+        x(board.width),
+        y(board.height),
+        x(0)
+    )
+    .unwrap();
+    if !thin.is_empty() {
+        writeln!(
+            out,
+            "<path d=\"{}\" stroke=\"#999\" stroke-width=\"1\" fill=\"none\"/>",
+            thin
+        )
+        .unwrap();
+    }
+    writeln!(
+        out,
+        "<path d=\"{}\" stroke=\"black\" stroke-width=\"3\" stroke-linecap=\"square\" \
+         fill=\"none\"/>",
+        thick
+    )
+    .unwrap();
+
+    for (i, _) in changed.iter().enumerate().filter(|(_, &changed)| changed) {
+        let (ix, iy) = (i % board.width, i / board.width);
+        writeln!(
+            out,
+            "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" stroke=\"{}\" \
+             stroke-width=\"3\" fill=\"none\"/>",
+            x(ix) + 3,
+            y(iy) + 3,
+            CELL - 6,
+            CELL - 6,
+            CHANGED
+        )
+        .unwrap();
+    }
+
+    text(
+        &mut out,
+        caption,
+        width / 2,
+        y(board.height) + CELL * 3 / 4,
+        font * 3 / 4,
+    );
+    writeln!(out, "</svg>").unwrap();
+    out
+}
+
+fn text(out: &mut String, text: &str, x: usize, y: usize, size: usize) {
+    let escaped = text
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;");
+    writeln!(
+        out,
+        "<text x=\"{}\" y=\"{}\" font-size=\"{}\">{}</text>",
+        x, y, size, escaped
+    )
+    .unwrap();
+}
+
+/// Draws a frame at every step, outlining what changed since the last
+struct Recorder {
+    step: Step,
+    /// The cell states at the last frame
+    last: Vec<CellState>,
+    frames: Vec<String>,
+}
+
+impl Recorder {
+    fn frame(&mut self, board: &Board, caption: &str) {
+        let states: Vec<_> = board.cells.iter().map(|cell| cell.state).collect();
+        let changed: Vec<_> = states
+            .iter()
+            .zip(&self.last)
+            .map(|(state, last)| state != last)
+            .collect();
+        self.frames.push(render(board, caption, &changed));
+        self.last = states;
+    }
+}
+
+impl SolverObserver for Recorder {
+    fn on_deduction(&mut self, board: &Board, deduction: &Deduction) -> bool {
+        if self.step == Step::Deduction {
+            self.frame(board, &deduction.to_string());
+        }
+        true
+    }
+
+    fn on_iteration_end(&mut self, board: &Board, iteration: usize, updated: bool) {
+        if self.step == Step::Iteration && updated {
+            self.frame(board, &format!("Pass {}", iteration));
+        }
+    }
+}
+
+/// Solve `board` from its current state, drawing the puzzle, a frame at every step, and the
+/// board once the search has finished anything the rules left
+pub fn frames(board: &Board, step: Step) -> Vec<String> {
+    let mut board = board.clone();
+    let mut recorder = Recorder {
+        step,
+        last: board.cells.iter().map(|cell| cell.state).collect(),
+        frames: Vec::new(),
+    };
+    recorder.frame(&board, "");
+    let stats = search::solve_observed(&mut board, &mut recorder);
+    // The search can finish a board with levels the rest force, without a single guess
+    let searched = board
+        .cells
+        .iter()
+        .zip(&recorder.last)
+        .any(|(cell, &last)| cell.state != last);
+    if searched || stats.guesses > 0 {
+        let caption = if !board.is_solved() {
+            format!("The search found no solution in {} guesses", stats.guesses)
+        } else if stats.guesses > 0 {
+            format!("The search finished it with {} guesses", stats.guesses)
+        } else {
+            "The search finished it without guessing".to_string()
+        };
+        recorder.frame(&board, &caption);
+    }
+    recorder.frames
+}
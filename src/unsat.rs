@@ -0,0 +1,166 @@
+//! Find which hints make a puzzle impossible.
+//!
+//! Starting from every hint, each hint in turn is left out if the puzzle stays impossible without
+//! it. What is left is a set of hints that can't all hold, and that could all hold with any one of
+//! them left out, so one of them is wrong.
+
+use crate::search;
+use crate::{Board, CellState};
+
+/// A set of hints that can't all hold at once
+pub struct HintConflict {
+    pub rows: Vec<usize>,
+    pub cols: Vec<usize>,
+    /// The aquariums lying in more than one of the lines, or in the line if there is only one
+    pub aquariums: Vec<usize>,
+}
+
+/// The hints that make the board impossible to complete from its current state, or `None` if it
+/// can be completed. With no hints at all, the cells already decided contradict the aquarium rules.
+pub fn conflicting_hints(board: &Board) -> Option<HintConflict> {
+    let n_lines = board.height + board.width;
+    let mut ignored = vec![false; n_lines];
+    if search::has_solution_ignoring(board, &ignored) {
+        return None;
+    }
+    for line in 0..n_lines {
+        ignored[line] = true;
+        if search::has_solution_ignoring(board, &ignored) {
+            ignored[line] = false;
+        }
+    }
+
+    let rows: Vec<_> = (0..board.height).filter(|&iy| !ignored[iy]).collect();
+    let cols: Vec<_> = (0..board.width)
+        .filter(|&ix| !ignored[board.height + ix])
+        .collect();
+    let lines_through = |partition| {
+        rows.iter()
+            .filter(|&&iy| (0..board.width).any(|ix| board.partition_at(ix, iy) == partition))
+            .count()
+            + cols
+                .iter()
+                .filter(|&&ix| (0..board.height).any(|iy| board.partition_at(ix, iy) == partition))
+                .count()
+    };
+    let shared = if rows.len() + cols.len() > 1 { 2 } else { 1 };
+    let aquariums = board
+        .partitions()
+        .into_iter()
+        .filter(|&partition| lines_through(partition) >= shared)
+        .collect();
+    Some(HintConflict {
+        rows,
+        cols,
+        aquariums,
+    })
+}
+
+impl HintConflict {
+    /// Say which hints conflict, e.g. "Row 4 hint 5 and column 2 hint 3 can't both hold;
+    /// they meet in aquarium 7"
+    pub fn describe(&self, board: &Board) -> String {
+        let mut hints: Vec<_> = self
+            .rows
+            .iter()
+            .map(|&iy| format!("row {} hint {}", iy, board.row_hints[iy]))
+            .collect();
+        hints.extend(
+            self.cols
+                .iter()
+                .map(|&ix| format!("column {} hint {}", ix, board.col_hints[ix])),
+        );
+        if hints.is_empty() {
+            return "The cells already filled in can't be completed".to_string();
+        }
+
+        let mut text = list(&hints);
+        text[..1].make_ascii_uppercase();
+        text += match hints.len() {
+            1 => " can't be met",
+            2 => " can't both hold",
+            _ => " can't all hold",
+        };
+        let decided = (0..board.height)
+            .any(|iy| (0..board.width).any(|ix| board.cell_state_at(ix, iy) != CellState::Empty));
+        if decided {
+            text += " with the cells already filled in";
+        }
+        if !self.aquariums.is_empty() {
+            let labels: Vec<_> = self
+                .aquariums
+                .iter()
+                .map(|&partition| board.partition_label(partition))
+                .collect();
+            text += &format!(
+                "; {} {} {}",
+                if hints.len() == 1 {
+                    "it runs through"
+                } else {
+                    "they meet in"
+                },
+                if labels.len() == 1 {
+                    "aquarium"
+                } else {
+                    "aquariums"
+                },
+                list(&labels)
+            );
+        }
+        text
+    }
+}
+
+/// "a", "a and b", "a, b and c"
+fn list(items: &[String]) -> String {
+    match items.split_last() {
+        Some((last, [])) => last.clone(),
+        Some((last, rest)) => format!("{} and {}", rest.join(", "), last),
+        None => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Which lines a conflict leaves out, rows first as `has_solution_ignoring` takes them
+    fn ignored(board: &Board, conflict: &HintConflict) -> Vec<bool> {
+        let mut ignored = vec![true; board.height + board.width];
+        for &iy in &conflict.rows {
+            ignored[iy] = false;
+        }
+        for &ix in &conflict.cols {
+            ignored[board.height + ix] = false;
+        }
+        ignored
+    }
+
+    #[test]
+    fn a_solvable_puzzle_has_no_conflict() {
+        assert!(conflicting_hints(&Board::make_b0()).is_none());
+    }
+
+    #[test]
+    fn the_conflict_is_minimal() {
+        let b0 = Board::make_b0();
+        for line in 0..b0.height + b0.width {
+            let mut board = b0.clone();
+            let (hint, most) = if line < board.height {
+                (&mut board.row_hints[line], b0.width)
+            } else {
+                (&mut board.col_hints[line - b0.height], b0.height)
+            };
+            *hint += if *hint < most as isize { 1 } else { -1 };
+
+            let conflict = conflicting_hints(&board).unwrap();
+            let ignored = ignored(&board, &conflict);
+            assert!(!search::has_solution_ignoring(&board, &ignored));
+            for member in (0..ignored.len()).filter(|&line| !ignored[line]) {
+                let mut without = ignored.clone();
+                without[member] = true;
+                assert!(search::has_solution_ignoring(&board, &without));
+            }
+        }
+    }
+}